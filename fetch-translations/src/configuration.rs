@@ -1,13 +1,40 @@
-use std::{fs::File, path::Path};
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+};
 
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
 use serde::Deserialize;
 
+use crate::poe::Quantity;
+
 #[derive(Debug, Deserialize)]
 pub struct Language {
     pub code: String,
     pub values_folder: String,
     pub store_listing_file: String,
+    // The plural categories the locale uses, see https://www.unicode.org/cldr/charts/latest/supplemental/language_plural_rules.html
+    #[serde(default = "default_plurals")]
+    pub plurals: Vec<Quantity>,
+}
+
+impl Language {
+    pub fn strings_xml_path(&self) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../app/src/main/res")
+            .join(&self.values_folder)
+            .join("strings.xml")
+    }
+
+    pub fn store_listing_path(&self) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../store-listing")
+            .join(&self.store_listing_file)
+    }
+}
+
+fn default_plurals() -> Vec<Quantity> {
+    vec![Quantity::One, Quantity::Other]
 }
 
 #[derive(Debug)]
@@ -26,9 +53,73 @@ impl Configuration {
         })
     }
 
+    pub fn languages(&self) -> &[Language] {
+        &self.supported_languages
+    }
+
     pub fn language_by_code(&self, code: &str) -> Option<&Language> {
         self.supported_languages
             .iter()
             .find(|language| language.code == code)
     }
 }
+
+const DEFAULT_BASE_URL: &str = "https://api.poeditor.com/v2";
+
+#[derive(Debug, Default, Deserialize)]
+struct PoEditorFile {
+    api_token: Option<String>,
+    project_id: Option<String>,
+    base_url: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct PoEditorSettings {
+    pub api_token: String,
+    pub project_id: String,
+    pub base_url: String,
+}
+
+impl PoEditorSettings {
+    pub fn default_file() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("poeditor.json")
+    }
+
+    // The values passed explicitly (command line flags, then environment variables) take
+    // precedence over the ones found in the config file.
+    pub fn resolve(
+        api_token: Option<String>,
+        project_id: Option<String>,
+        base_url: Option<String>,
+        config_file: &Path,
+    ) -> Result<PoEditorSettings> {
+        let file = if config_file.exists() {
+            serde_json::from_str::<PoEditorFile>(&fs::read_to_string(config_file)?)
+                .with_context(|| format!("Couldn't parse {}", config_file.display()))?
+        } else {
+            PoEditorFile::default()
+        };
+
+        let api_token = api_token.or(file.api_token).ok_or_else(|| {
+            anyhow!(
+                "Missing POEditor API token: pass --api-token, set POEDITOR_API_TOKEN, or add \"api_token\" to {}",
+                config_file.display()
+            )
+        })?;
+        let project_id = project_id.or(file.project_id).ok_or_else(|| {
+            anyhow!(
+                "Missing POEditor project id: pass --project-id, set POEDITOR_PROJECT_ID, or add \"project_id\" to {}",
+                config_file.display()
+            )
+        })?;
+        let base_url = base_url
+            .or(file.base_url)
+            .unwrap_or_else(|| DEFAULT_BASE_URL.to_string());
+
+        Ok(PoEditorSettings {
+            api_token,
+            project_id,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        })
+    }
+}
@@ -0,0 +1,105 @@
+use anyhow::Result;
+use similar::TextDiff;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    inputs::{read_json, read_xml},
+    outputs::Value,
+};
+
+const PROJECT_DIR: &str = env!("CARGO_MANIFEST_DIR");
+
+#[derive(Debug, Clone, Copy)]
+pub enum Format {
+    Xml,
+    Json,
+}
+
+#[derive(Debug)]
+pub struct FileDiff {
+    pub path: String,
+    pub added: usize,
+    pub removed: usize,
+    pub changed: usize,
+    pub unified: String,
+}
+
+impl FileDiff {
+    // Compares what would be written to `path` with what is already on disk.
+    // Returns `None` when both are identical.
+    pub fn compute(path: &Path, rendered: &str, format: Format) -> Result<Option<Self>> {
+        let existing = if path.exists() {
+            fs::read_to_string(path)?
+        } else {
+            String::new()
+        };
+        if existing == rendered {
+            return Ok(None);
+        }
+
+        let label = display_path(path);
+        let unified = TextDiff::from_lines(existing.as_str(), rendered)
+            .unified_diff()
+            .context_radius(2)
+            .header(&format!("a/{label}"), &format!("b/{label}"))
+            .to_string();
+
+        let old_entries = entries(&existing, format)?;
+        let new_entries = entries(rendered, format)?;
+
+        let added = new_entries
+            .keys()
+            .filter(|key| !old_entries.contains_key(*key))
+            .count();
+        let removed = old_entries
+            .keys()
+            .filter(|key| !new_entries.contains_key(*key))
+            .count();
+        let changed = new_entries
+            .iter()
+            .filter(|(key, value)| matches!(old_entries.get(*key), Some(old) if old != *value))
+            .count();
+
+        Ok(Some(Self {
+            path: label,
+            added,
+            removed,
+            changed,
+            unified,
+        }))
+    }
+
+    pub fn print(&self) {
+        println!(
+            "{}: {} added, {} removed, {} changed",
+            self.path, self.added, self.removed, self.changed
+        );
+        print!("{}", self.unified);
+    }
+}
+
+fn display_path(path: &Path) -> String {
+    let root = PathBuf::from(PROJECT_DIR).join("..");
+    path.strip_prefix(&root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn entries(content: &str, format: Format) -> Result<BTreeMap<String, Value>> {
+    if content.trim().is_empty() {
+        return Ok(BTreeMap::new());
+    }
+
+    match format {
+        Format::Json => read_json(content),
+        Format::Xml => Ok(read_xml(content)?
+            .into_iter()
+            .map(|term| (term.key, term.value))
+            .collect()),
+    }
+}
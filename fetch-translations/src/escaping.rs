@@ -0,0 +1,364 @@
+// Android string resource escaping, see
+// https://developer.android.com/guide/topics/resources/string-resource#escaping_quotes
+//
+// The text held by `Term`s is what the user sees. `escape` turns it into the content of a
+// `<string>` element, `unescape` does what aapt does when reading it back.
+
+// Escapes a value and wraps it in double quotes, so that whitespace and apostrophes are kept as is.
+pub fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+    escaped.push('"');
+    for (index, c) in value.chars().enumerate() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            // A leading @ or ? would make the value a reference to another resource.
+            '@' | '?' if index == 0 => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c if needs_unicode_escape(c) => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+// Splits an escaped value in CDATA sections, as a section can't contain `]]>`.
+pub fn cdata_sections(escaped: &str) -> Vec<&str> {
+    let mut sections = Vec::new();
+    let mut rest = escaped;
+    while let Some(index) = rest.find("]]>") {
+        // End the section between `]]` and `>`.
+        sections.push(&rest[..index + 2]);
+        rest = &rest[index + 2..];
+    }
+    sections.push(rest);
+    sections
+}
+
+// Reads the content of a `<string>` element like aapt does: quotes are removed and keep the
+// whitespace they surround, other whitespace is collapsed and trimmed, and escape sequences are
+// resolved.
+pub fn unescape(raw: &str) -> String {
+    let mut value = String::with_capacity(raw.len());
+    let mut in_quotes = false;
+    let mut pending_space = false;
+    let mut chars = raw.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let resolved = match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                continue;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                pending_space = true;
+                continue;
+            }
+            '\\' => match chars.next() {
+                Some('n') => '\n',
+                Some('t') => '\t',
+                Some('u') => match parse_unicode(&mut chars) {
+                    Some(c) => c,
+                    None => continue,
+                },
+                Some(other) => other,
+                None => continue,
+            },
+            c => c,
+        };
+        if pending_space && !value.is_empty() {
+            value.push(' ');
+        }
+        pending_space = false;
+        value.push(resolved);
+    }
+
+    value
+}
+
+// Resolves the escape sequences translators type in POEditor, like `\n` or `\'`. Invalid sequences
+// are kept as is, `invalid_sequences` reports them.
+pub fn decode(content: &str) -> String {
+    let mut value = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        match chars.peek().copied() {
+            Some('n') => {
+                chars.next();
+                value.push('\n');
+            }
+            Some('t') => {
+                chars.next();
+                value.push('\t');
+            }
+            Some(escaped @ ('\'' | '"' | '\\' | '@' | '?')) => {
+                chars.next();
+                value.push(escaped);
+            }
+            Some('u') => {
+                let mut lookahead = chars.clone();
+                lookahead.next();
+                match parse_unicode(&mut lookahead) {
+                    Some(unicode) => {
+                        chars = lookahead;
+                        value.push(unicode);
+                    }
+                    None => value.push('\\'),
+                }
+            }
+            _ => value.push('\\'),
+        }
+    }
+
+    value
+}
+
+// The reverse of `decode`, for values sent to POEditor.
+pub fn encode(value: &str) -> String {
+    value.replace('\\', "\\\\")
+}
+
+// The escape sequences of a POEditor content that Android would reject.
+pub fn invalid_sequences(content: &str) -> Vec<String> {
+    let mut errors = Vec::new();
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 't' | '\'' | '"' | '\\' | '@' | '?') => {}
+            Some('u') => {
+                let digits: String = chars.clone().take(4).collect();
+                if parse_unicode(&mut chars).is_none() {
+                    errors.push(format!("malformed unicode escape \\u{digits}"));
+                }
+            }
+            Some(other) => errors.push(format!("unknown escape sequence \\{other}")),
+            None => errors.push("trailing backslash".to_string()),
+        }
+    }
+    errors
+}
+
+fn needs_unicode_escape(c: char) -> bool {
+    c.is_control() || c == '\u{2028}' || c == '\u{2029}'
+}
+
+fn parse_unicode<I>(chars: &mut std::iter::Peekable<I>) -> Option<char>
+where
+    I: Iterator<Item = char> + Clone,
+{
+    let digits: String = chars.clone().take(4).collect();
+    if digits.len() != 4 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let c = char::from_u32(u32::from_str_radix(&digits, 16).ok()?)?;
+    for _ in 0..4 {
+        chars.next();
+    }
+    Some(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_wraps_in_quotes() {
+        assert_eq!(escape("Hello"), "\"Hello\"");
+        assert_eq!(escape(""), "\"\"");
+    }
+
+    #[test]
+    fn escape_keeps_apostrophes() {
+        assert_eq!(escape("Couldn't launch"), "\"Couldn't launch\"");
+    }
+
+    #[test]
+    fn escape_double_quotes() {
+        assert_eq!(
+            escape("Click the \"Open GIF\" button."),
+            "\"Click the \\\"Open GIF\\\" button.\""
+        );
+    }
+
+    #[test]
+    fn escape_backslashes() {
+        assert_eq!(escape("C:\\gifs"), "\"C:\\\\gifs\"");
+        assert_eq!(escape("\\n"), "\"\\\\n\"");
+    }
+
+    #[test]
+    fn escape_new_lines_and_tabs() {
+        assert_eq!(escape("One\nTwo"), "\"One\\nTwo\"");
+        assert_eq!(escape("One\tTwo"), "\"One\\tTwo\"");
+    }
+
+    #[test]
+    fn escape_keeps_whitespace() {
+        assert_eq!(escape("  spaced  out "), "\"  spaced  out \"");
+    }
+
+    #[test]
+    fn escape_leading_reference_characters() {
+        assert_eq!(escape("@string/app_name"), "\"\\@string/app_name\"");
+        assert_eq!(escape("?attr/color"), "\"\\?attr/color\"");
+    }
+
+    #[test]
+    fn escape_keeps_reference_characters_after_the_start() {
+        assert_eq!(escape("me@example.com?"), "\"me@example.com?\"");
+    }
+
+    #[test]
+    fn escape_control_characters_as_unicode() {
+        assert_eq!(escape("a\u{0007}b"), "\"a\\u0007b\"");
+        assert_eq!(escape("a\u{2028}b"), "\"a\\u2028b\"");
+    }
+
+    #[test]
+    fn escape_keeps_non_ascii() {
+        assert_eq!(escape("Über die App…"), "\"Über die App…\"");
+        assert_eq!(escape("GIF 动态壁纸"), "\"GIF 动态壁纸\"");
+    }
+
+    #[test]
+    fn escape_keeps_placeholders() {
+        assert_eq!(escape("%1$s of %2$d, 100%%"), "\"%1$s of %2$d, 100%%\"");
+    }
+
+    #[test]
+    fn escape_keeps_markup() {
+        assert_eq!(
+            escape("<b>Bold</b> & <a href=\"https://redwarp.app\">link</a>"),
+            "\"<b>Bold</b> & <a href=\\\"https://redwarp.app\\\">link</a>\""
+        );
+    }
+
+    #[test]
+    fn cdata_sections_without_terminator() {
+        assert_eq!(cdata_sections("\"<b>Bold</b>\""), vec!["\"<b>Bold</b>\""]);
+    }
+
+    #[test]
+    fn cdata_sections_split_terminator() {
+        assert_eq!(cdata_sections("a]]>b"), vec!["a]]", ">b"]);
+        assert_eq!(cdata_sections("]]>]]>"), vec!["]]", ">]]", ">"]);
+    }
+
+    #[test]
+    fn unescape_removes_quotes() {
+        assert_eq!(unescape("\"Hello\""), "Hello");
+        assert_eq!(unescape("\"Don't\" stop"), "Don't stop");
+    }
+
+    #[test]
+    fn unescape_escaped_quotes_and_apostrophes() {
+        assert_eq!(unescape("Don\\'t"), "Don't");
+        assert_eq!(unescape("\"Say \\\"hi\\\"\""), "Say \"hi\"");
+    }
+
+    #[test]
+    fn unescape_collapses_unquoted_whitespace() {
+        assert_eq!(unescape("  One \n   Two  "), "One Two");
+    }
+
+    #[test]
+    fn unescape_keeps_quoted_whitespace() {
+        assert_eq!(unescape("\"One \n   Two \""), "One \n   Two ");
+    }
+
+    #[test]
+    fn unescape_sequences() {
+        assert_eq!(unescape("One\\nTwo\\tThree"), "One\nTwo\tThree");
+        assert_eq!(unescape("\\@home \\?attr \\\\"), "@home ?attr \\");
+        assert_eq!(unescape("\\u00e9t\\u00E9"), "été");
+    }
+
+    #[test]
+    fn unescape_drops_malformed_unicode() {
+        assert_eq!(unescape("a\\u12b"), "a12b");
+    }
+
+    #[test]
+    fn unescape_reverses_escape() {
+        for value in [
+            "",
+            "Hello",
+            "Couldn't launch the \"wallpaper\" chooser.",
+            "  leading and trailing  ",
+            "Line\nbreak\tand tab",
+            "@string/app_name",
+            "?attr/color",
+            "C:\\gifs\\new",
+            "\\n is not a new line",
+            "a\u{0007}b\u{2028}c",
+            "<b>%1$s</b> & 100%%",
+            "]]>",
+        ] {
+            assert_eq!(unescape(&escape(value)), value, "{value:?}");
+        }
+    }
+
+    #[test]
+    fn decode_sequences() {
+        assert_eq!(decode("One\\nTwo\\tThree"), "One\nTwo\tThree");
+        assert_eq!(decode("Don\\'t say \\\"hi\\\""), "Don't say \"hi\"");
+        assert_eq!(decode("\\@ \\? \\\\"), "@ ? \\");
+        assert_eq!(decode("\\u00e9"), "é");
+    }
+
+    #[test]
+    fn decode_keeps_invalid_sequences() {
+        assert_eq!(decode("\\x \\u12 end\\"), "\\x \\u12 end\\");
+    }
+
+    #[test]
+    fn decode_keeps_quotes_and_whitespace() {
+        assert_eq!(decode("  \"quoted\"  "), "  \"quoted\"  ");
+    }
+
+    #[test]
+    fn decode_reverses_encode() {
+        for value in ["", "plain", "C:\\gifs", "\\n", "\\\\", "\\u00e9", "end\\"] {
+            assert_eq!(decode(&encode(value)), value, "{value:?}");
+        }
+    }
+
+    #[test]
+    fn invalid_sequences_accepts_valid_ones() {
+        assert!(invalid_sequences("\\n \\t \\' \\\" \\\\ \\@ \\? \\u00e9").is_empty());
+    }
+
+    #[test]
+    fn invalid_sequences_reports_unknown_escapes() {
+        assert_eq!(
+            invalid_sequences("\\x and \\d"),
+            vec!["unknown escape sequence \\x", "unknown escape sequence \\d"]
+        );
+    }
+
+    #[test]
+    fn invalid_sequences_reports_malformed_unicode() {
+        assert_eq!(
+            invalid_sequences("\\u12g4"),
+            vec!["malformed unicode escape \\u12g4"]
+        );
+    }
+
+    #[test]
+    fn invalid_sequences_reports_trailing_backslash() {
+        assert_eq!(invalid_sequences("end\\"), vec!["trailing backslash"]);
+    }
+}
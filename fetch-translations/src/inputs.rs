@@ -0,0 +1,348 @@
+use anyhow::{anyhow, Result};
+use std::{collections::BTreeMap, fs};
+use xml::{
+    escape::escape_str_attribute,
+    reader::{EventReader, XmlEvent},
+};
+
+use crate::{
+    configuration::Language,
+    escaping::unescape,
+    outputs::{StringType, Strings, Term, Value},
+    poe::Quantity,
+};
+
+impl<'a> Strings<'a> {
+    // Loads the strings currently on disk for the language: the app strings from its
+    // `strings.xml`, and the store strings from its store listing file.
+    pub fn read(language: &'a Language) -> Result<Self> {
+        let mut terms: BTreeMap<String, Term> = BTreeMap::new();
+
+        let xml_path = language.strings_xml_path();
+        if xml_path.exists() {
+            for term in read_xml(&fs::read_to_string(xml_path)?)? {
+                terms.insert(term.key.clone(), term);
+            }
+        }
+
+        let json_path = language.store_listing_path();
+        if json_path.exists() {
+            for (key, value) in read_json(&fs::read_to_string(json_path)?)? {
+                terms
+                    .entry(key.clone())
+                    .or_insert_with(|| Term {
+                        key,
+                        value,
+                        string_types: Vec::new(),
+                        translatable: true,
+                        invalid_sequences: Vec::new(),
+                    })
+                    .string_types
+                    .push(StringType::Store);
+            }
+        }
+
+        Ok(Strings {
+            language,
+            terms: terms.into_values().collect(),
+        })
+    }
+}
+
+pub fn read_json(content: &str) -> Result<BTreeMap<String, Value>> {
+    let values: BTreeMap<String, String> = serde_json::from_str(content)?;
+
+    Ok(values
+        .into_iter()
+        .map(|(key, value)| (key, Value::Singular(value)))
+        .collect())
+}
+
+// The resource being read, with the items read so far for plurals and arrays.
+enum Resource {
+    String,
+    Plurals(BTreeMap<Quantity, String>),
+    Array(Vec<String>),
+}
+
+// Reads the `<string>`, `<plurals>` and `<string-array>` resources of an Android resource file.
+// Inline markup like `<b>` or `<xliff:g>` is kept in the text of the resource, which is then
+// styled.
+pub fn read_xml(content: &str) -> Result<Vec<Term>> {
+    let mut terms = Vec::new();
+    let mut current: Option<(String, bool, Resource)> = None;
+    let mut quantity: Option<Quantity> = None;
+    let mut text = String::new();
+    let mut cdata = false;
+    let mut styled = false;
+
+    for event in EventReader::from_str(content) {
+        match event? {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => {
+                let attribute = |key: &str| {
+                    attributes
+                        .iter()
+                        .find(|attribute| attribute.name.local_name == key)
+                        .map(|attribute| attribute.value.as_str())
+                };
+                let element = name.local_name.as_str();
+                // Inline markup of a resource, like `<b>`, is kept in its text.
+                if current.is_some() && element != "item" {
+                    styled = true;
+                    text.push('<');
+                    text.push_str(&name.borrow().to_repr());
+                    // The quotes are escaped, as unescape drops the bare ones.
+                    for attribute in &attributes {
+                        text.push_str(&format!(
+                            " {}=\\\"{}\\\"",
+                            attribute.name.borrow().to_repr(),
+                            escape_str_attribute(&attribute.value)
+                        ));
+                    }
+                    text.push('>');
+                    continue;
+                }
+
+                let resource = match element {
+                    "string" => Some(Resource::String),
+                    "plurals" => Some(Resource::Plurals(BTreeMap::new())),
+                    "string-array" => Some(Resource::Array(Vec::new())),
+                    "item" => {
+                        quantity = attribute("quantity")
+                            .map(|value| {
+                                Quantity::parse(value)
+                                    .ok_or_else(|| anyhow!("Unknown plural quantity {value}"))
+                            })
+                            .transpose()?;
+                        None
+                    }
+                    _ => None,
+                };
+                if let Some(resource) = resource {
+                    let key = attribute("name").ok_or_else(|| {
+                        anyhow!("Missing name attribute on <{}>", name.local_name)
+                    })?;
+                    let translatable = attribute("translatable") != Some("false");
+                    current = Some((key.to_string(), translatable, resource));
+                    cdata = false;
+                    styled = false;
+                }
+                if matches!(element, "string" | "plurals" | "string-array" | "item") {
+                    text.clear();
+                }
+            }
+            XmlEvent::Characters(characters) => text.push_str(&characters),
+            XmlEvent::CData(characters) => {
+                cdata = true;
+                text.push_str(&characters)
+            }
+            XmlEvent::Whitespace(whitespace) => text.push_str(&whitespace),
+            XmlEvent::EndElement { name } => match (name.local_name.as_str(), current.as_mut()) {
+                ("item", Some((_, _, Resource::Plurals(items)))) => {
+                    let quantity = quantity
+                        .take()
+                        .ok_or_else(|| anyhow!("Missing quantity attribute on a plural item"))?;
+                    items.insert(quantity, unescape(&text));
+                }
+                ("item", Some((_, _, Resource::Array(items)))) => items.push(unescape(&text)),
+                ("string" | "plurals" | "string-array", Some(_)) => {
+                    if let Some((key, translatable, resource)) = current.take() {
+                        let value = match resource {
+                            Resource::String => Value::Singular(unescape(&text)),
+                            Resource::Plurals(items) => Value::Plural(items),
+                            Resource::Array(items) => Value::Array(items),
+                        };
+                        let mut string_types = vec![StringType::App];
+                        if cdata {
+                            string_types.push(StringType::Html);
+                        }
+                        if styled {
+                            string_types.push(StringType::Styled);
+                        }
+                        terms.push(Term {
+                            key,
+                            value,
+                            string_types,
+                            translatable,
+                            invalid_sequences: Vec::new(),
+                        });
+                    }
+                }
+                (_, Some(_)) => {
+                    text.push_str("</");
+                    text.push_str(&name.borrow().to_repr());
+                    text.push('>');
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    Ok(terms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STRINGS_XML: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<resources xmlns:xliff="urn:oasis:names:tc:xliff:document:1.2">
+    <string name="app_name" translatable="false">GIF Wallpaper</string>
+    <string name="open_gif">"Click the \"Open GIF\" button."</string>
+    <string name="welcome">Hello <b>you</b>, see <a href="https://example.com?a=1&amp;b=2">this</a></string>
+    <string name="set_as">Set as <xliff:g id="screen" example="home">%1$s</xliff:g></string>
+    <plurals name="gif_count">
+        <item quantity="one">One <i>GIF</i></item>
+        <item quantity="other">%d GIFs</item>
+    </plurals>
+    <string-array name="scales">
+        <item>Fit</item>
+        <item><b>Fill</b></item>
+    </string-array>
+    <string name="notice"><![CDATA[<b>Bold</b> & co]]></string>
+</resources>
+"#;
+
+    fn language() -> Language {
+        Language {
+            code: "en".to_string(),
+            values_folder: "values".to_string(),
+            store_listing_file: "en-US.json".to_string(),
+            plurals: vec![Quantity::One, Quantity::Other],
+        }
+    }
+
+    fn round_trip(content: &str) -> (Vec<Term>, String) {
+        let language = language();
+        let strings = Strings {
+            language: &language,
+            terms: read_xml(content).unwrap(),
+        };
+        let rendered = strings.render_xml().unwrap();
+        (strings.terms, rendered)
+    }
+
+    fn term<'a>(terms: &'a [Term], key: &str) -> &'a Term {
+        terms.iter().find(|term| term.key == key).unwrap()
+    }
+
+    #[test]
+    fn round_trip_keeps_values_and_types() {
+        let (terms, rendered) = round_trip(STRINGS_XML);
+        let read_back = read_xml(&rendered).unwrap();
+
+        assert_eq!(terms.len(), read_back.len());
+        for (term, read_back) in terms.iter().zip(&read_back) {
+            assert_eq!(term.key, read_back.key);
+            assert_eq!(term.value, read_back.value);
+            assert_eq!(term.string_types, read_back.string_types);
+            assert_eq!(term.translatable, read_back.translatable);
+        }
+    }
+
+    #[test]
+    fn round_trip_is_stable() {
+        let (_, rendered) = round_trip(STRINGS_XML);
+        let (_, rendered_again) = round_trip(&rendered);
+
+        assert_eq!(rendered, rendered_again);
+    }
+
+    #[test]
+    fn inline_markup_is_styled() {
+        let terms = read_xml(STRINGS_XML).unwrap();
+
+        assert_eq!(
+            term(&terms, "welcome").value,
+            Value::Singular(
+                "Hello <b>you</b>, see <a href=\"https://example.com?a=1&amp;b=2\">this</a>"
+                    .to_string()
+            )
+        );
+        assert!(term(&terms, "welcome")
+            .string_types
+            .contains(&StringType::Styled));
+        assert!(!term(&terms, "open_gif")
+            .string_types
+            .contains(&StringType::Styled));
+    }
+
+    #[test]
+    fn inline_markup_is_written_as_elements() {
+        let (_, rendered) = round_trip(STRINGS_XML);
+
+        assert!(rendered.contains(
+            r#"<string name="welcome">"Hello "<b>"you"</b>", see "<a href="https://example.com?a=1&amp;b=2">"this"</a></string>"#
+        ));
+        assert!(rendered.contains(r#"<xliff:g id="screen" example="home">"%1$s"</xliff:g>"#));
+        assert!(rendered.contains(r#"xmlns:xliff="urn:oasis:names:tc:xliff:document:1.2""#));
+        assert!(!rendered.contains("&lt;b"));
+    }
+
+    #[test]
+    fn html_strings_stay_cdata() {
+        let (terms, rendered) = round_trip(STRINGS_XML);
+
+        assert_eq!(
+            term(&terms, "notice").value,
+            Value::Singular("<b>Bold</b> & co".to_string())
+        );
+        assert!(
+            rendered.contains(r#"<string name="notice"><![CDATA["<b>Bold</b> & co"]]></string>"#)
+        );
+    }
+
+    #[test]
+    fn round_trip_keeps_plurals_and_arrays() {
+        let (_, rendered) = round_trip(STRINGS_XML);
+        let terms = read_xml(&rendered).unwrap();
+
+        assert_eq!(
+            term(&terms, "gif_count").value,
+            Value::Plural(BTreeMap::from([
+                (Quantity::One, "One <i>GIF</i>".to_string()),
+                (Quantity::Other, "%d GIFs".to_string()),
+            ]))
+        );
+        assert_eq!(
+            term(&terms, "scales").value,
+            Value::Array(vec!["Fit".to_string(), "<b>Fill</b>".to_string()])
+        );
+    }
+
+    #[test]
+    fn round_trip_keeps_untranslatable_strings() {
+        let (_, rendered) = round_trip(STRINGS_XML);
+        let terms = read_xml(&rendered).unwrap();
+
+        assert!(rendered
+            .contains(r#"<string name="app_name" translatable="false">"GIF Wallpaper"</string>"#));
+        assert!(!term(&terms, "app_name").translatable);
+        assert!(term(&terms, "open_gif").translatable);
+    }
+
+    #[test]
+    fn unbalanced_markup_is_escaped() {
+        let language = language();
+        let strings = Strings {
+            language: &language,
+            terms: vec![Term {
+                key: "broken".to_string(),
+                value: Value::Singular("<b>Bold".to_string()),
+                string_types: vec![StringType::App, StringType::Styled],
+                translatable: true,
+                invalid_sequences: Vec::new(),
+            }],
+        };
+        let rendered = strings.render_xml().unwrap();
+
+        assert!(rendered.contains(r#"<string name="broken">"&lt;b&gt;Bold"</string>"#));
+        assert_eq!(
+            read_xml(&rendered).unwrap()[0].value,
+            Value::Singular("<b>Bold".to_string())
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+use xml::reader::{EventReader, XmlEvent};
+
+use crate::{
+    poe::{Content, Term, Translation},
+    provider::TranslationProvider,
+};
+
+const TAGS_FILE: &str = "tags.json";
+const EXTENSIONS: [&str; 3] = ["json", "xliff", "xlf"];
+
+// Reads POEditor exports from a directory, one `<language code>.json` or `<language code>.xliff`
+// file per language. The exports don't contain the tags, so unless the JSON entries have a `tags`
+// field, they are read from a `tags.json` file mapping each term to its tags.
+pub struct LocalExports {
+    directory: PathBuf,
+}
+
+#[derive(Deserialize, Debug)]
+struct ExportedTerm {
+    term: String,
+    #[serde(default)]
+    context: String,
+    definition: Option<Content>,
+    tags: Option<Vec<String>>,
+}
+
+impl LocalExports {
+    pub fn new(directory: &Path) -> Self {
+        Self {
+            directory: directory.to_path_buf(),
+        }
+    }
+
+    fn tags(&self) -> Result<HashMap<String, Vec<String>>> {
+        let tags_file = self.directory.join(TAGS_FILE);
+        if tags_file.exists() {
+            Ok(serde_json::from_str(&fs::read_to_string(tags_file)?)?)
+        } else {
+            Ok(HashMap::new())
+        }
+    }
+
+    fn export_file(&self, language_code: &str) -> Option<PathBuf> {
+        EXTENSIONS
+            .iter()
+            .map(|extension| self.directory.join(format!("{language_code}.{extension}")))
+            .find(|path| path.exists())
+    }
+}
+
+impl TranslationProvider for LocalExports {
+    async fn languages(&self) -> Result<Vec<String>> {
+        let mut languages: Vec<String> = fs::read_dir(&self.directory)?
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.file_name().is_some_and(|name| name != TAGS_FILE)
+                    && path.extension().is_some_and(|extension| {
+                        EXTENSIONS.iter().any(|supported| extension == *supported)
+                    })
+            })
+            .filter_map(|path| {
+                path.file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+            })
+            .collect();
+        languages.sort();
+        languages.dedup();
+
+        Ok(languages)
+    }
+
+    async fn terms(&self, language_code: &str) -> Result<Vec<Term>> {
+        let file = self.export_file(language_code).ok_or_else(|| {
+            anyhow!(
+                "No export found for language {language_code} in {}",
+                self.directory.display()
+            )
+        })?;
+        let content = fs::read_to_string(&file)?;
+        let tags = self.tags()?;
+
+        let terms = match file.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => parse_json(&content)?,
+            _ => parse_xliff(&content)?,
+        };
+
+        Ok(terms
+            .into_iter()
+            .map(|exported| {
+                let tags = exported
+                    .tags
+                    .or_else(|| tags.get(&exported.term).cloned())
+                    .unwrap_or_default();
+                Term {
+                    term: exported.term,
+                    context: exported.context,
+                    translation: Translation {
+                        content: exported
+                            .definition
+                            .unwrap_or_else(|| Content::Singular(String::new())),
+                    },
+                    tags,
+                }
+            })
+            .collect())
+    }
+}
+
+fn parse_json(content: &str) -> Result<Vec<ExportedTerm>> {
+    Ok(serde_json::from_str(content)?)
+}
+
+// Only singular terms are supported, POEditor exports plurals as gettext groups that can't be
+// mapped back to quantities without the plural rules: use the JSON export for those.
+fn parse_xliff(content: &str) -> Result<Vec<ExportedTerm>> {
+    let mut terms = Vec::new();
+    let mut current: Option<ExportedTerm> = None;
+    let mut in_target = false;
+
+    for event in EventReader::from_str(content) {
+        match event? {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => match name.local_name.as_str() {
+                "trans-unit" => {
+                    let attribute = |key: &str| {
+                        attributes
+                            .iter()
+                            .find(|attribute| attribute.name.local_name == key)
+                            .map(|attribute| attribute.value.clone())
+                    };
+                    current = attribute("resname")
+                        .or_else(|| attribute("id"))
+                        .map(|term| ExportedTerm {
+                            term,
+                            context: String::new(),
+                            definition: None,
+                            tags: None,
+                        });
+                }
+                "target" => in_target = true,
+                _ => {}
+            },
+            XmlEvent::Characters(text) | XmlEvent::CData(text) if in_target => {
+                if let Some(term) = current.as_mut() {
+                    match term.definition.as_mut() {
+                        Some(Content::Singular(definition)) => definition.push_str(&text),
+                        _ => term.definition = Some(Content::Singular(text)),
+                    }
+                }
+            }
+            XmlEvent::EndElement { name } => match name.local_name.as_str() {
+                "trans-unit" => terms.extend(current.take()),
+                "target" => in_target = false,
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    Ok(terms)
+}
@@ -1,85 +1,227 @@
-use crate::configuration::Configuration;
+use crate::configuration::{Configuration, PoEditorSettings};
+use crate::diff::{FileDiff, Format};
+use crate::local::LocalExports;
 use crate::outputs::Strings;
-use anyhow::Result;
-use poe::TermResponse;
-use reqwest::Client;
-use serde::Deserialize;
+use crate::poe::PoEditor;
+use crate::provider::TranslationProvider;
+use crate::report::OutputFormat;
+use crate::validation::Severity;
+use anyhow::{anyhow, Result};
+use clap::{Args as ClapArgs, Parser, Subcommand};
+use std::path::PathBuf;
 
 mod configuration;
+mod diff;
+mod escaping;
+mod inputs;
+mod local;
 mod outputs;
 mod poe;
-
-#[derive(Deserialize, Debug)]
-struct Language {
-    code: String,
+mod provider;
+mod report;
+mod upload;
+mod validation;
+
+const SOURCE_LANGUAGE: &str = "en";
+
+#[derive(Parser, Debug)]
+#[command(
+    about = "Fetch the translations from POEditor into the Android resources and store listings"
+)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Print a diff against the files on disk instead of writing them.
+    /// Exits with an error if anything differs.
+    #[arg(long, visible_alias = "diff")]
+    dry_run: bool,
+    /// Read POEditor JSON or XLIFF exports from this directory instead of calling the API.
+    #[arg(long, global = true, value_name = "DIR")]
+    from: Option<PathBuf>,
+    #[command(flatten)]
+    poeditor: PoEditorArgs,
 }
 
-#[derive(Deserialize, Debug)]
-struct LanguageResult {
-    languages: Vec<Language>,
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Push the English strings.xml and store listing to POEditor.
+    Upload {
+        /// Only print what would be sent to POEditor.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Report the missing, empty and untranslated strings of every configured language.
+    Report {
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+        /// Fail if the coverage of any language is below this percentage.
+        #[arg(long, value_name = "PERCENT")]
+        threshold: Option<f64>,
+    },
 }
 
-#[derive(Deserialize, Debug)]
-struct LanguageResponse {
-    result: LanguageResult,
+#[derive(ClapArgs, Debug)]
+struct PoEditorArgs {
+    /// The POEditor API token.
+    #[arg(
+        long,
+        global = true,
+        env = "POEDITOR_API_TOKEN",
+        hide_env_values = true
+    )]
+    api_token: Option<String>,
+    /// The POEditor project id.
+    #[arg(long, global = true, env = "POEDITOR_PROJECT_ID")]
+    project_id: Option<String>,
+    /// The POEditor API base URL, to point at a mock server for example.
+    #[arg(long, global = true, env = "POEDITOR_BASE_URL")]
+    base_url: Option<String>,
+    /// A JSON file with `api_token`, `project_id` and `base_url` keys, used for the values not
+    /// passed as flags or environment variables. Defaults to `fetch-translations/poeditor.json`.
+    #[arg(long, global = true, value_name = "FILE")]
+    config: Option<PathBuf>,
 }
 
-// Should probably be passed as an arg, but it's read only so I don't care enough.
-static API_TOKEN: &str = "259aa66dfbdccf0ed87bf09855e3a861";
-static PROJECT_ID: &str = "362629";
+impl PoEditorArgs {
+    fn settings(&self) -> Result<PoEditorSettings> {
+        PoEditorSettings::resolve(
+            self.api_token.clone(),
+            self.project_id.clone(),
+            self.base_url.clone(),
+            &self
+                .config
+                .clone()
+                .unwrap_or_else(PoEditorSettings::default_file),
+        )
+    }
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let args = Args::parse();
+
+    match (&args.command, &args.from) {
+        (Some(Command::Upload { .. }), Some(_)) => Err(anyhow!(
+            "upload always sends the strings to POEditor, it can't be combined with --from"
+        )),
+        (Some(Command::Upload { dry_run }), None) => {
+            let configuration = Configuration::load_configuration()?;
+            let language = configuration
+                .language_by_code(SOURCE_LANGUAGE)
+                .ok_or_else(|| anyhow!("No {SOURCE_LANGUAGE} language in config.json"))?;
+            let source = Strings::read(language)?;
+            let poeditor = PoEditor::new(args.poeditor.settings()?);
+            upload::upload(&poeditor, &source, *dry_run).await
+        }
+        (_, Some(directory)) => run(&args, &LocalExports::new(directory)).await,
+        (_, None) => run(&args, &PoEditor::new(args.poeditor.settings()?)).await,
+    }
+}
+
+async fn run<P: TranslationProvider>(args: &Args, provider: &P) -> Result<()> {
     let configuration = Configuration::load_configuration()?;
 
-    let client = reqwest::Client::new();
+    if let Some(Command::Report { format, threshold }) = &args.command {
+        return report::report(
+            provider,
+            &configuration,
+            SOURCE_LANGUAGE,
+            *format,
+            *threshold,
+        )
+        .await;
+    }
 
-    let content = fetch_languages(&client).await?;
+    let content = provider.languages().await?;
 
     println!("Printing fetched content: {content:?}");
 
+    let mut all_strings = Vec::new();
     for language in &content {
-        match configuration.language_by_code(&language.code) {
+        match configuration.language_by_code(language) {
             Some(supported_language) => {
-                let terms = fetch_terms(&client, &language.code).await?;
-
-                let strings = Strings::from(supported_language, terms);
-                strings.write_xml()?;
-                strings.write_json()?;
+                let terms = provider.terms(language).await?;
+                all_strings.push(Strings::from(supported_language, terms));
             }
-            None => println!("Unsupported language {}", language.code),
+            None => println!("Unsupported language {language}"),
         }
     }
 
+    validate(&all_strings, &configuration, provider).await?;
+
+    let mut diffs = Vec::new();
+    for strings in &all_strings {
+        if args.dry_run {
+            diffs.extend(FileDiff::compute(
+                &strings.xml_file_path(),
+                &strings.render_xml()?,
+                Format::Xml,
+            )?);
+            diffs.extend(FileDiff::compute(
+                &strings.json_file_path(),
+                &strings.render_json()?,
+                Format::Json,
+            )?);
+        } else {
+            strings.write_xml()?;
+            strings.write_json()?;
+        }
+    }
+
+    if args.dry_run {
+        for diff in &diffs {
+            diff.print();
+        }
+        if !diffs.is_empty() {
+            return Err(anyhow!(
+                "{} file(s) differ from the fetched translations",
+                diffs.len()
+            ));
+        }
+        println!("All files are up to date");
+    }
+
     Ok(())
 }
 
-async fn fetch_languages(client: &Client) -> Result<Vec<Language>> {
-    let response = client
-        .post("https://api.poeditor.com/v2/languages/list")
-        .form(&[("api_token", API_TOKEN), ("id", PROJECT_ID)])
-        .send()
-        .await?
-        .json::<LanguageResponse>()
-        .await?;
+// Nothing is written if any translation has an error, warnings are only printed.
+async fn validate<P: TranslationProvider>(
+    all_strings: &[Strings<'_>],
+    configuration: &Configuration,
+    provider: &P,
+) -> Result<()> {
+    let fetched_source = all_strings
+        .iter()
+        .find(|strings| strings.language.code == SOURCE_LANGUAGE);
+    let source = match fetched_source {
+        Some(source) => source,
+        None => {
+            let language = configuration
+                .language_by_code(SOURCE_LANGUAGE)
+                .ok_or_else(|| anyhow!("No {SOURCE_LANGUAGE} language in config.json"))?;
+            &Strings::from(language, provider.terms(SOURCE_LANGUAGE).await?)
+        }
+    };
+
+    let issues: Vec<_> = all_strings
+        .iter()
+        .filter(|strings| strings.language.code != SOURCE_LANGUAGE)
+        .chain(std::iter::once(source))
+        .flat_map(|strings| validation::validate(source, strings))
+        .collect();
+    for issue in &issues {
+        println!("{issue}");
+    }
 
-    Ok(response.result.languages)
-}
+    let errors = issues
+        .iter()
+        .filter(|issue| issue.severity == Severity::Error)
+        .count();
+    if errors > 0 {
+        return Err(anyhow!(
+            "{errors} translation error(s) found, nothing was written"
+        ));
+    }
 
-async fn fetch_terms(client: &Client, language_code: &str) -> Result<TermResponse> {
-    // Must use untagged for the plural stuff: https://github.com/serde-rs/json/issues/473
-    // as the content can be either string, or plural. We don't have plurals for now, so... ignore.
-    let response = client
-        .post("https://api.poeditor.com/v2/terms/list")
-        .form(&[
-            ("api_token", API_TOKEN),
-            ("id", PROJECT_ID),
-            ("language", language_code),
-        ])
-        .send()
-        .await?
-        .json::<TermResponse>()
-        .await?;
-
-    Ok(response)
+    Ok(())
 }
@@ -1,16 +1,28 @@
 use anyhow::Result;
 use convert_case::{Case, Casing};
+use regex::Regex;
 use serde_json::json;
 use std::{
-    collections::HashMap,
-    fs::{self, create_dir_all, File},
+    collections::{BTreeMap, HashMap},
+    fs::{self, create_dir_all},
+    io::Write,
     path::PathBuf,
+    sync::OnceLock,
+};
+use xml::{
+    escape::escape_str_pcdata,
+    writer::{EmitterConfig, EventWriter, XmlEvent},
 };
-use xml::writer::{EmitterConfig, XmlEvent};
 
-use crate::{configuration::Language, poe::TermResponse};
+use crate::{
+    configuration::Language,
+    escaping::{cdata_sections, decode, escape, invalid_sequences},
+    poe::{Content, Quantity, Term as PoeTerm},
+};
 
-const PROJECT_DIR: &str = env!("CARGO_MANIFEST_DIR");
+const XLIFF_NAMESPACE: &str = "urn:oasis:names:tc:xliff:document:1.2";
+// An opening, closing or empty tag, with its name and attributes.
+const TAG_REGEX: &str = r#"<(/)?([a-zA-Z][\w:.-]*)(?:\s+[\w:.-]+\s*=\s*"[^"]*")*\s*(/)?>"#;
 
 #[derive(Debug)]
 pub struct Strings<'a> {
@@ -18,18 +30,20 @@ pub struct Strings<'a> {
     pub terms: Vec<Term>,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum StringType {
     App,
     Store,
+    // Written as CDATA, for strings meant for `Html.fromHtml`.
+    Html,
+    // Has inline markup like `<b>` or `<xliff:g>`, written back as elements.
+    Styled,
     Other,
 }
 
 impl<'a> Strings<'a> {
-    pub fn from(language: &'a Language, response: TermResponse) -> Self {
-        let mut res: Vec<Term> = response
-            .result
-            .terms
+    pub fn from(language: &'a Language, terms: Vec<PoeTerm>) -> Self {
+        let mut res: Vec<Term> = terms
             .into_iter()
             .map(|term| {
                 let string_types: Vec<_> = term
@@ -38,13 +52,25 @@ impl<'a> Strings<'a> {
                     .map(|tag| match tag.as_str() {
                         "app" => StringType::App,
                         "store" => StringType::Store,
+                        "html" => StringType::Html,
+                        "styled" => StringType::Styled,
                         _ => StringType::Other,
                     })
                     .collect();
+                // Checked before decoding, as a decoded `\\` would look like an escape.
+                let invalid_sequences = match &term.translation.content {
+                    Content::Singular(content) => invalid_sequences(content),
+                    Content::Plural(content) => content
+                        .values()
+                        .flat_map(|content| invalid_sequences(content))
+                        .collect(),
+                };
                 Term {
                     key: term.term,
-                    value: term.translation.content.trim().to_string(),
+                    value: term.translation.content.into(),
                     string_types,
+                    translatable: true,
+                    invalid_sequences,
                 }
             })
             .collect();
@@ -56,58 +82,254 @@ impl<'a> Strings<'a> {
         }
     }
 
+    pub fn json_file_path(&self) -> PathBuf {
+        self.language.store_listing_path()
+    }
+
+    pub fn xml_file_path(&self) -> PathBuf {
+        self.language.strings_xml_path()
+    }
+
     pub fn write_json(&self) -> Result<()> {
-        let file_path = PathBuf::from(PROJECT_DIR)
-            .join("../store-listing")
-            .join(&self.language.store_listing_file);
+        fs::write(self.json_file_path(), self.render_json()?)?;
+
+        Ok(())
+    }
+
+    pub fn write_xml(&self) -> Result<()> {
+        let file_path = self.xml_file_path();
+        if let Some(folder) = file_path.parent() {
+            create_dir_all(folder)?;
+        }
+        fs::write(file_path, self.render_xml()?)?;
+
+        Ok(())
+    }
 
+    pub fn render_json(&self) -> Result<String> {
         let map: HashMap<_, _> = self
             .terms
             .iter()
-            .filter(|term| term.string_types.contains(&StringType::Store) && !term.value.is_empty())
-            .map(|term| (term.key.clone(), term.value.clone()))
+            .filter(|term| term.string_types.contains(&StringType::Store))
+            .filter_map(|term| match &term.value {
+                Value::Singular(value) if !value.is_empty() => {
+                    Some((term.key.clone(), value.clone()))
+                }
+                _ => None,
+            })
             .collect();
 
         let json = json!(map);
-        fs::write(file_path, serde_json::to_string_pretty(&json)?)?;
 
-        Ok(())
+        Ok(serde_json::to_string_pretty(&json)?)
     }
 
-    pub fn write_xml(&self) -> Result<()> {
-        let folder = PathBuf::from(PROJECT_DIR)
-            .join("../app/src/main/res")
-            .join(&self.language.values_folder);
-        create_dir_all(&folder)?;
-        let file_path = folder.join("strings.xml");
-
-        let mut file = File::create(file_path)?;
+    pub fn render_xml(&self) -> Result<String> {
+        let mut output = Vec::new();
         let mut writer = EmitterConfig::new()
             .perform_indent(true)
-            .create_writer(&mut file);
+            .create_writer(&mut output);
 
-        writer.write(XmlEvent::start_element("resources"))?;
-        for term in self
+        let terms: Vec<_> = self
             .terms
             .iter()
             .filter(|term| term.string_types.contains(&StringType::App) && !term.value.is_empty())
-        {
+            .collect();
+        let uses_xliff = terms.iter().any(|term| {
+            term.string_types.contains(&StringType::Styled) && term.value.contains("<xliff:")
+        });
+        let resources = XmlEvent::start_element("resources");
+        writer.write(if uses_xliff {
+            resources.ns("xliff", XLIFF_NAMESPACE)
+        } else {
+            resources
+        })?;
+        for term in terms {
             let key = term.key.to_case(Case::Snake);
-            let value = format!("\"{}\"", term.value.replace('"', "\\\""));
-            let start = XmlEvent::start_element("string").attr("name", key.as_str());
-            writer.write(start)?;
-            writer.write(XmlEvent::characters(value.as_str()))?;
-            writer.write(XmlEvent::end_element())?;
+            let mode = if term.string_types.contains(&StringType::Html) {
+                Mode::CData
+            } else if term.string_types.contains(&StringType::Styled) {
+                Mode::Styled
+            } else {
+                Mode::Text
+            };
+            let start_element = |name| {
+                let start = XmlEvent::start_element(name).attr("name", key.as_str());
+                if term.translatable {
+                    start
+                } else {
+                    start.attr("translatable", "false")
+                }
+            };
+            match &term.value {
+                Value::Singular(value) => {
+                    writer.write(start_element("string"))?;
+                    write_value(&mut writer, value, mode)?;
+                    writer.write(XmlEvent::end_element())?;
+                }
+                Value::Plural(values) => {
+                    // Only emit the quantities the locale actually uses, Android ignores the others.
+                    let items: Vec<_> = self
+                        .language
+                        .plurals
+                        .iter()
+                        .filter_map(|quantity| values.get(quantity).map(|value| (quantity, value)))
+                        .collect();
+                    if items.is_empty() {
+                        continue;
+                    }
+
+                    writer.write(start_element("plurals"))?;
+                    for (quantity, value) in items {
+                        let start =
+                            XmlEvent::start_element("item").attr("quantity", quantity.as_str());
+                        writer.write(start)?;
+                        write_value(&mut writer, value, mode)?;
+                        writer.write(XmlEvent::end_element())?;
+                    }
+                    writer.write(XmlEvent::end_element())?;
+                }
+                Value::Array(values) => {
+                    writer.write(start_element("string-array"))?;
+                    for value in values {
+                        writer.write(XmlEvent::start_element("item"))?;
+                        write_value(&mut writer, value, mode)?;
+                        writer.write(XmlEvent::end_element())?;
+                    }
+                    writer.write(XmlEvent::end_element())?;
+                }
+            }
         }
         writer.write(XmlEvent::end_element())?;
 
-        Ok(())
+        Ok(String::from_utf8(output)?)
     }
 }
 
 #[derive(Debug)]
 pub struct Term {
-    key: String,
-    value: String,
-    string_types: Vec<StringType>,
+    pub key: String,
+    pub value: Value,
+    pub string_types: Vec<StringType>,
+    // Only false for resources read from disk marked with `translatable="false"`.
+    pub translatable: bool,
+    // The escape sequences of the POEditor content that Android would reject, empty for the
+    // strings read from disk.
+    pub invalid_sequences: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Singular(String),
+    Plural(BTreeMap<Quantity, String>),
+    Array(Vec<String>),
+}
+
+impl From<Content> for Value {
+    fn from(content: Content) -> Self {
+        match content {
+            Content::Singular(content) => Value::Singular(decode(content.trim())),
+            Content::Plural(content) => Value::Plural(
+                content
+                    .into_iter()
+                    .map(|(quantity, content)| (quantity, decode(content.trim())))
+                    .filter(|(_, content)| !content.is_empty())
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl Value {
+    // Whether one of the strings has the given text, for the namespaces of the markup.
+    fn contains(&self, text: &str) -> bool {
+        match self {
+            Value::Singular(value) => value.contains(text),
+            Value::Plural(values) => values.values().any(|value| value.contains(text)),
+            Value::Array(values) => values.iter().any(|value| value.contains(text)),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Value::Singular(value) => value.is_empty(),
+            Value::Plural(values) => values.is_empty(),
+            Value::Array(values) => values.is_empty(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Mode {
+    Text,
+    CData,
+    Styled,
+}
+
+fn write_value<W: Write>(writer: &mut EventWriter<W>, value: &str, mode: Mode) -> Result<()> {
+    let escaped = escape(value);
+    match mode {
+        Mode::Styled => match markup_sections(value) {
+            Some(sections) => {
+                // Closes the start tag, the markup is then written as is.
+                writer.write(XmlEvent::characters(""))?;
+                for section in sections {
+                    let raw = match section {
+                        Section::Text(text) => escape_str_pcdata(&escape(text)).into_owned(),
+                        Section::Markup(markup) => markup.to_string(),
+                    };
+                    writer.inner_mut().write_all(raw.as_bytes())?;
+                }
+            }
+            // Markup that isn't balanced would break the file, it is kept as text.
+            None => writer.write(XmlEvent::characters(&escaped))?,
+        },
+        Mode::Text => writer.write(XmlEvent::characters(&escaped))?,
+        Mode::CData => {
+            for section in cdata_sections(&escaped) {
+                writer.write(XmlEvent::cdata(section))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+enum Section<'a> {
+    Text(&'a str),
+    Markup(&'a str),
+}
+
+// Splits a styled string into its text and its tags, or None if the tags aren't balanced.
+fn markup_sections(value: &str) -> Option<Vec<Section<'_>>> {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    let regex = REGEX.get_or_init(|| Regex::new(TAG_REGEX).expect("Invalid tag regex"));
+
+    let mut sections = Vec::new();
+    let mut open: Vec<&str> = Vec::new();
+    let mut last = 0;
+    for captures in regex.captures_iter(value) {
+        let tag = captures.get(0)?;
+        let name = captures.get(2)?.as_str();
+        if captures.get(1).is_some() {
+            if open.pop() != Some(name) {
+                return None;
+            }
+        } else if captures.get(3).is_none() {
+            open.push(name);
+        }
+        if tag.start() > last {
+            sections.push(Section::Text(&value[last..tag.start()]));
+        }
+        sections.push(Section::Markup(tag.as_str()));
+        last = tag.end();
+    }
+    if !open.is_empty() {
+        return None;
+    }
+    if last < value.len() {
+        sections.push(Section::Text(&value[last..]));
+    }
+
+    Some(sections)
 }
@@ -1,22 +1,209 @@
-use serde::Deserialize;
+use std::collections::BTreeMap;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+use crate::{configuration::PoEditorSettings, provider::TranslationProvider};
+
+/// The CLDR plural categories, as used by both POEditor and Android's `quantity` attribute.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Quantity {
+    Zero,
+    One,
+    Two,
+    Few,
+    Many,
+    Other,
+}
+
+impl Quantity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Quantity::Zero => "zero",
+            Quantity::One => "one",
+            Quantity::Two => "two",
+            Quantity::Few => "few",
+            Quantity::Many => "many",
+            Quantity::Other => "other",
+        }
+    }
+
+    pub fn parse(quantity: &str) -> Option<Self> {
+        match quantity {
+            "zero" => Some(Quantity::Zero),
+            "one" => Some(Quantity::One),
+            "two" => Some(Quantity::Two),
+            "few" => Some(Quantity::Few),
+            "many" => Some(Quantity::Many),
+            "other" => Some(Quantity::Other),
+            _ => None,
+        }
+    }
+}
+
+// Must use untagged for the plural stuff: https://github.com/serde-rs/json/issues/473
+// as the content can be either a string, or an object keyed by quantity.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum Content {
+    Singular(String),
+    Plural(BTreeMap<Quantity, String>),
+}
+
+#[derive(Deserialize, Debug)]
+struct Language {
+    code: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct LanguageResult {
+    languages: Vec<Language>,
+}
+
+#[derive(Deserialize, Debug)]
+struct LanguageResponse {
+    result: LanguageResult,
+}
 
 #[derive(Deserialize, Debug)]
 pub struct Translation {
-    pub content: String,
+    pub content: Content,
 }
 
 #[derive(Deserialize, Debug)]
 pub struct Term {
     pub term: String,
+    #[serde(default)]
+    pub context: String,
     pub translation: Translation,
     pub tags: Vec<String>,
 }
 
 #[derive(Deserialize, Debug)]
-pub struct TermResult {
-    pub terms: Vec<Term>,
+struct TermResult {
+    terms: Vec<Term>,
+}
+#[derive(Deserialize, Debug)]
+struct TermResponse {
+    result: TermResult,
+}
+
+#[derive(Deserialize, Debug)]
+struct Status {
+    status: String,
+    message: String,
 }
+
 #[derive(Deserialize, Debug)]
-pub struct TermResponse {
-    pub result: TermResult,
+struct StatusResponse {
+    response: Status,
+}
+
+pub struct PoEditor {
+    client: Client,
+    settings: PoEditorSettings,
+}
+
+impl PoEditor {
+    pub fn new(settings: PoEditorSettings) -> Self {
+        Self {
+            client: Client::new(),
+            settings,
+        }
+    }
+}
+
+impl TranslationProvider for PoEditor {
+    async fn languages(&self) -> Result<Vec<String>> {
+        let response = self
+            .client
+            .post(format!("{}/languages/list", self.settings.base_url))
+            .form(&[
+                ("api_token", &self.settings.api_token),
+                ("id", &self.settings.project_id),
+            ])
+            .send()
+            .await?
+            .json::<LanguageResponse>()
+            .await?;
+
+        Ok(response
+            .result
+            .languages
+            .into_iter()
+            .map(|language| language.code)
+            .collect())
+    }
+
+    async fn terms(&self, language_code: &str) -> Result<Vec<Term>> {
+        let response = self
+            .client
+            .post(format!("{}/terms/list", self.settings.base_url))
+            .form(&[
+                ("api_token", self.settings.api_token.as_str()),
+                ("id", self.settings.project_id.as_str()),
+                ("language", language_code),
+            ])
+            .send()
+            .await?
+            .json::<TermResponse>()
+            .await?;
+
+        Ok(response.result.terms)
+    }
+}
+
+impl PoEditor {
+    pub async fn add_terms(&self, data: &serde_json::Value) -> Result<()> {
+        self.post_data("terms/add", &[], data).await
+    }
+
+    pub async fn update_terms(&self, data: &serde_json::Value) -> Result<()> {
+        self.post_data("terms/update", &[], data).await
+    }
+
+    pub async fn update_translations(
+        &self,
+        language_code: &str,
+        data: &serde_json::Value,
+    ) -> Result<()> {
+        self.post_data("languages/update", &[("language", language_code)], data)
+            .await
+    }
+
+    async fn post_data(
+        &self,
+        endpoint: &str,
+        parameters: &[(&str, &str)],
+        data: &serde_json::Value,
+    ) -> Result<()> {
+        let data = data.to_string();
+        let mut form = vec![
+            ("api_token", self.settings.api_token.as_str()),
+            ("id", self.settings.project_id.as_str()),
+            ("data", data.as_str()),
+        ];
+        form.extend_from_slice(parameters);
+
+        let response = self
+            .client
+            .post(format!("{}/{endpoint}", self.settings.base_url))
+            .form(&form)
+            .send()
+            .await?
+            .json::<StatusResponse>()
+            .await?;
+
+        if response.response.status == "success" {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "POEditor {endpoint} failed: {}",
+                response.response.message
+            ))
+        }
+    }
 }
@@ -0,0 +1,10 @@
+use anyhow::Result;
+
+use crate::poe::Term;
+
+// Where the translations come from: POEditor itself, or exports sitting on disk.
+pub trait TranslationProvider {
+    async fn languages(&self) -> Result<Vec<String>>;
+
+    async fn terms(&self, language_code: &str) -> Result<Vec<Term>>;
+}
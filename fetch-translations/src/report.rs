@@ -0,0 +1,293 @@
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use convert_case::{Case, Casing};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    configuration::{Configuration, Language},
+    outputs::{StringType, Strings, Term, Value},
+    provider::TranslationProvider,
+};
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum OutputFormat {
+    Table,
+    Json,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct Gaps {
+    pub missing: Vec<String>,
+    pub empty: Vec<String>,
+    pub untranslated: Vec<String>,
+}
+
+impl Gaps {
+    fn count(&self) -> usize {
+        self.missing.len() + self.empty.len() + self.untranslated.len()
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct LanguageReport {
+    pub code: String,
+    pub total: usize,
+    pub translated: usize,
+    pub coverage: f64,
+    pub app: Gaps,
+    pub store: Gaps,
+}
+
+impl LanguageReport {
+    // The untranslatable keys are the ones of the source strings.xml, like `app_name`.
+    fn new(
+        language: &Language,
+        source: &[Term],
+        terms: &[Term],
+        untranslatable: &HashSet<String>,
+    ) -> Self {
+        let terms: HashMap<&str, &Term> =
+            terms.iter().map(|term| (term.key.as_str(), term)).collect();
+        let mut app = Gaps::default();
+        let mut store = Gaps::default();
+        let mut total = 0;
+        let mut translated = 0;
+
+        for source_term in source {
+            let is_app = source_term.string_types.contains(&StringType::App);
+            let is_store = source_term.string_types.contains(&StringType::Store);
+            if (!is_app && !is_store)
+                || untranslatable.contains(&source_term.key.to_case(Case::Snake))
+            {
+                continue;
+            }
+            total += 1;
+
+            let gap = match terms.get(source_term.key.as_str()) {
+                None => Some(Gap::Missing),
+                Some(term) if term.value.is_empty() => Some(Gap::Empty),
+                Some(term) if is_untranslated(&source_term.value, &term.value) => {
+                    Some(Gap::Untranslated)
+                }
+                Some(_) => None,
+            };
+            match gap {
+                Some(gap) => {
+                    if is_app {
+                        gap.add_to(&mut app, &source_term.key);
+                    }
+                    if is_store {
+                        gap.add_to(&mut store, &source_term.key);
+                    }
+                }
+                None => translated += 1,
+            }
+        }
+
+        let coverage = if total == 0 {
+            100.0
+        } else {
+            translated as f64 * 100.0 / total as f64
+        };
+
+        Self {
+            code: language.code.clone(),
+            total,
+            translated,
+            coverage,
+            app,
+            store,
+        }
+    }
+
+    fn print(&self) {
+        println!(
+            "{:<10} {:>4}/{:<4} {:>6.1}%  app: {:>3} missing {:>3} empty {:>3} untranslated  store: {:>3} missing {:>3} empty {:>3} untranslated",
+            self.code,
+            self.translated,
+            self.total,
+            self.coverage,
+            self.app.missing.len(),
+            self.app.empty.len(),
+            self.app.untranslated.len(),
+            self.store.missing.len(),
+            self.store.empty.len(),
+            self.store.untranslated.len(),
+        );
+    }
+
+    fn print_details(&self) {
+        if self.app.count() + self.store.count() == 0 {
+            return;
+        }
+        println!("{}:", self.code);
+        for (name, gaps) in [("app", &self.app), ("store", &self.store)] {
+            for (kind, keys) in [
+                ("missing", &gaps.missing),
+                ("empty", &gaps.empty),
+                ("untranslated", &gaps.untranslated),
+            ] {
+                if !keys.is_empty() {
+                    println!("  {name} {kind}: {}", keys.join(", "));
+                }
+            }
+        }
+    }
+}
+
+enum Gap {
+    Missing,
+    Empty,
+    Untranslated,
+}
+
+impl Gap {
+    fn add_to(&self, gaps: &mut Gaps, key: &str) {
+        let keys = match self {
+            Gap::Missing => &mut gaps.missing,
+            Gap::Empty => &mut gaps.empty,
+            Gap::Untranslated => &mut gaps.untranslated,
+        };
+        keys.push(key.to_string());
+    }
+}
+
+fn is_untranslated(source: &Value, translation: &Value) -> bool {
+    match (source, translation) {
+        (Value::Singular(source), Value::Singular(translation)) => source == translation,
+        // A plural is untranslated if every quantity is still the English one.
+        (Value::Plural(source), Value::Plural(translation)) => translation
+            .iter()
+            .all(|(quantity, translation)| source.get(quantity) == Some(translation)),
+        _ => false,
+    }
+}
+
+// Compares every configured language with the source language, failing when the coverage of any
+// language is below the threshold, in percent.
+pub async fn report<P: TranslationProvider>(
+    provider: &P,
+    configuration: &Configuration,
+    source_language: &str,
+    format: OutputFormat,
+    threshold: Option<f64>,
+) -> Result<()> {
+    let source_language = configuration
+        .language_by_code(source_language)
+        .ok_or_else(|| anyhow!("No {source_language} language in config.json"))?;
+    let source = Strings::from(
+        source_language,
+        provider.terms(&source_language.code).await?,
+    );
+    let available = provider.languages().await?;
+    // POEditor doesn't know which strings are `translatable="false"`, only the source file does.
+    let untranslatable: HashSet<String> = Strings::read(source_language)?
+        .terms
+        .into_iter()
+        .filter(|term| !term.translatable)
+        .map(|term| term.key)
+        .collect();
+
+    let mut reports = Vec::new();
+    for language in configuration.languages() {
+        if language.code == source_language.code {
+            continue;
+        }
+        let terms = if available.contains(&language.code) {
+            Strings::from(language, provider.terms(&language.code).await?).terms
+        } else {
+            Vec::new()
+        };
+        reports.push(LanguageReport::new(
+            language,
+            &source.terms,
+            &terms,
+            &untranslatable,
+        ));
+    }
+
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&reports)?),
+        OutputFormat::Table => {
+            for report in &reports {
+                report.print();
+            }
+            println!();
+            for report in &reports {
+                report.print_details();
+            }
+        }
+    }
+
+    if let Some(threshold) = threshold {
+        let below: Vec<_> = reports
+            .iter()
+            .filter(|report| report.coverage < threshold)
+            .map(|report| format!("{} ({:.1}%)", report.code, report.coverage))
+            .collect();
+        if !below.is_empty() {
+            return Err(anyhow!(
+                "Coverage below {threshold}% for {}",
+                below.join(", ")
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poe::Quantity;
+
+    fn language() -> Language {
+        Language {
+            code: "fr".to_string(),
+            values_folder: "values-fr".to_string(),
+            store_listing_file: "fr-FR.json".to_string(),
+            plurals: vec![Quantity::One, Quantity::Other],
+        }
+    }
+
+    fn term(key: &str, value: &str) -> Term {
+        Term {
+            key: key.to_string(),
+            value: Value::Singular(value.to_string()),
+            string_types: vec![StringType::App],
+            translatable: true,
+            invalid_sequences: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn strings_equal_to_the_source_are_untranslated() {
+        let source = [term("app_name", "GIF Wallpaper"), term("open", "Open")];
+        let terms = [term("app_name", "GIF Wallpaper"), term("open", "Ouvrir")];
+
+        let report = LanguageReport::new(&language(), &source, &terms, &HashSet::new());
+
+        assert_eq!(report.total, 2);
+        assert_eq!(report.translated, 1);
+        assert_eq!(report.app.untranslated, ["app_name"]);
+    }
+
+    #[test]
+    fn untranslatable_strings_are_skipped() {
+        let source = [
+            term("appName", "GIF Wallpaper"),
+            term("open", "Open"),
+            term("close", "Close"),
+        ];
+        let terms = [term("appName", "GIF Wallpaper"), term("open", "Ouvrir")];
+        let untranslatable = HashSet::from(["app_name".to_string()]);
+
+        let report = LanguageReport::new(&language(), &source, &terms, &untranslatable);
+
+        assert_eq!(report.total, 2);
+        assert_eq!(report.translated, 1);
+        assert!(report.app.untranslated.is_empty());
+        assert_eq!(report.app.missing, ["close"]);
+    }
+}
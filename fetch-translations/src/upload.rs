@@ -0,0 +1,184 @@
+use anyhow::Result;
+use convert_case::{Case, Casing};
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::{
+    escaping::encode,
+    outputs::{StringType, Strings, Term, Value},
+    poe::{PoEditor, Term as PoeTerm},
+    provider::TranslationProvider,
+};
+
+const MANAGED_TAGS: [&str; 2] = ["app", "store"];
+
+#[derive(Debug, Default)]
+pub struct UploadPlan {
+    added: Vec<serde_json::Value>,
+    retagged: Vec<serde_json::Value>,
+    translated: Vec<serde_json::Value>,
+    obsolete: Vec<String>,
+}
+
+impl UploadPlan {
+    // Compares the source strings with the terms currently in the POEditor project.
+    pub fn new(source: &Strings, remote: &[PoeTerm]) -> Self {
+        let mut plan = UploadPlan::default();
+        let remote_by_key: HashMap<String, &PoeTerm> = remote
+            .iter()
+            .map(|term| (term.term.to_case(Case::Snake), term))
+            .collect();
+
+        for term in &source.terms {
+            if !term.translatable {
+                continue;
+            }
+            if let Value::Array(_) = term.value {
+                println!("Skipping {}, string arrays can't be uploaded", term.key);
+                continue;
+            }
+            let tags = tags(term);
+            match remote_by_key.get(&term.key) {
+                None => {
+                    let mut added = json!({
+                        "term": term.key,
+                        "context": "",
+                        "tags": tags,
+                    });
+                    if let Value::Plural(_) = term.value {
+                        added["plural"] = json!(term.key);
+                    }
+                    plan.added.push(added);
+                    plan.translated
+                        .push(translation(&term.key, "", &term.value));
+                }
+                Some(remote_term) => {
+                    // Keep the tags that are not ours, and replace the app and store ones.
+                    let remote_tags: BTreeSet<&str> =
+                        remote_term.tags.iter().map(String::as_str).collect();
+                    let wanted_tags: BTreeSet<&str> = remote_tags
+                        .iter()
+                        .copied()
+                        .filter(|tag| !MANAGED_TAGS.contains(tag))
+                        .chain(tags.iter().copied())
+                        .collect();
+                    if wanted_tags != remote_tags {
+                        plan.retagged.push(json!({
+                            "term": remote_term.term,
+                            "context": remote_term.context,
+                            "new_term": remote_term.term,
+                            "tags": wanted_tags,
+                        }));
+                    }
+
+                    let remote_value: Value = Value::from(remote_term.translation.content.clone());
+                    if remote_value != term.value {
+                        plan.translated.push(translation(
+                            &remote_term.term,
+                            &remote_term.context,
+                            &term.value,
+                        ));
+                    }
+                }
+            }
+        }
+
+        let source_keys: BTreeSet<&str> =
+            source.terms.iter().map(|term| term.key.as_str()).collect();
+        plan.obsolete = remote
+            .iter()
+            .filter(|term| {
+                term.tags
+                    .iter()
+                    .any(|tag| MANAGED_TAGS.contains(&tag.as_str()))
+            })
+            .filter(|term| !source_keys.contains(term.term.to_case(Case::Snake).as_str()))
+            .map(|term| term.term.clone())
+            .collect();
+        plan.obsolete.sort();
+
+        plan
+    }
+
+    pub fn print(&self) {
+        print_terms("Terms to add", &self.added);
+        print_terms("Terms to retag", &self.retagged);
+        print_terms("Source translations to update", &self.translated);
+        if !self.obsolete.is_empty() {
+            println!(
+                "Terms that would become obsolete, remove them from POEditor if they are not used anymore:"
+            );
+            for term in &self.obsolete {
+                println!("  {term}");
+            }
+        }
+    }
+
+    pub async fn apply(&self, poeditor: &PoEditor, language_code: &str) -> Result<()> {
+        if !self.added.is_empty() {
+            poeditor.add_terms(&json!(self.added)).await?;
+        }
+        if !self.retagged.is_empty() {
+            poeditor.update_terms(&json!(self.retagged)).await?;
+        }
+        if !self.translated.is_empty() {
+            poeditor
+                .update_translations(language_code, &json!(self.translated))
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+pub async fn upload(poeditor: &PoEditor, source: &Strings<'_>, dry_run: bool) -> Result<()> {
+    let remote = poeditor.terms(&source.language.code).await?;
+    let plan = UploadPlan::new(source, &remote);
+    plan.print();
+
+    if !dry_run {
+        plan.apply(poeditor, &source.language.code).await?;
+        println!("Uploaded the {} source strings", source.language.code);
+    }
+
+    Ok(())
+}
+
+fn tags(term: &Term) -> Vec<&'static str> {
+    term.string_types
+        .iter()
+        .filter_map(|string_type| match string_type {
+            StringType::App => Some("app"),
+            StringType::Store => Some("store"),
+            StringType::Html => Some("html"),
+            StringType::Styled => Some("styled"),
+            StringType::Other => None,
+        })
+        .collect()
+}
+
+fn translation(key: &str, context: &str, value: &Value) -> serde_json::Value {
+    let content = match value {
+        Value::Singular(value) => json!(encode(value)),
+        Value::Plural(values) => json!(values
+            .iter()
+            .map(|(quantity, value)| (quantity, encode(value)))
+            .collect::<BTreeMap<_, _>>()),
+        Value::Array(values) => json!(values.iter().map(|value| encode(value)).collect::<Vec<_>>()),
+    };
+    json!({
+        "term": key,
+        "context": context,
+        "translation": { "content": content },
+    })
+}
+
+fn print_terms(title: &str, terms: &[serde_json::Value]) {
+    if terms.is_empty() {
+        return;
+    }
+    println!("{title}:");
+    for term in terms {
+        println!("  {}", term["term"].as_str().unwrap_or_default());
+    }
+}
@@ -0,0 +1,343 @@
+use regex::Regex;
+use std::{collections::BTreeSet, fmt::Display, sync::OnceLock};
+
+use crate::{
+    outputs::{Strings, Term, Value},
+    poe::Quantity,
+};
+
+// Same syntax as java.util.Formatter, which is what Android's getString(id, args) relies on, with
+// its conversions only. Dates are `%t` or `%T` followed by a suffix.
+const PLACEHOLDER_REGEX: &str =
+    r"%(?:(\d+)\$)?([-#+ 0,(<]*)\d*(?:\.\d+)?([bBhHsScCdoxXeEfgGaA%n]|[tT][a-zA-Z])";
+const MARKUP_REGEX: &str = r"<(/?)([a-zA-Z]+)[^>]*>";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug)]
+pub struct Issue {
+    pub severity: Severity,
+    pub language: String,
+    pub key: String,
+    pub message: String,
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(
+            f,
+            "{severity}: [{}] {}: {}",
+            self.language, self.key, self.message
+        )
+    }
+}
+
+// Checks the translated terms against the source ones, so that a translation can't crash the
+// app at runtime or lose its formatting.
+pub fn validate(source: &Strings, translation: &Strings) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    for term in &translation.terms {
+        let source_term = source
+            .terms
+            .iter()
+            .find(|source_term| source_term.key == term.key);
+
+        let mut report = |severity, message: String| {
+            issues.push(Issue {
+                severity,
+                language: translation.language.code.clone(),
+                key: term.key.clone(),
+                message,
+            })
+        };
+
+        for message in &term.invalid_sequences {
+            report(Severity::Error, message.clone());
+        }
+
+        if let Some(source_term) = source_term {
+            check_against_source(source_term, term, &mut report);
+        }
+    }
+
+    issues
+}
+
+fn check_against_source(
+    source: &Term,
+    translation: &Term,
+    report: &mut impl FnMut(Severity, String),
+) {
+    // Terms that aren't translated yet come back empty and singular, whatever their kind. The
+    // report already lists them.
+    if translation.value.is_empty() {
+        return;
+    }
+
+    match (&source.value, &translation.value) {
+        (Value::Singular(source), Value::Singular(translation)) => {
+            let expected = placeholders(source);
+            let actual = placeholders(translation);
+            if expected != actual {
+                report(
+                    Severity::Error,
+                    format!(
+                        "placeholders {} don't match the source {}",
+                        describe(&actual),
+                        describe(&expected)
+                    ),
+                );
+            }
+            check_layout(source, translation, report);
+        }
+        (Value::Plural(source), Value::Plural(translation)) => {
+            // Some languages drop the number for the `one` quantity, so a plural item can have
+            // fewer placeholders than the source, never more.
+            let Some(source_other) = source.get(&Quantity::Other) else {
+                return;
+            };
+            let expected = placeholders(source_other);
+            for (quantity, translation) in translation {
+                let actual = placeholders(translation);
+                if !actual.is_subset(&expected) {
+                    report(
+                        Severity::Error,
+                        format!(
+                            "placeholders {} of quantity {} are not in the source {}",
+                            describe(&actual),
+                            quantity.as_str(),
+                            describe(&expected)
+                        ),
+                    );
+                }
+                let source = source.get(quantity).unwrap_or(source_other);
+                check_layout(source, translation, report);
+            }
+        }
+        _ => report(
+            Severity::Error,
+            "the translation and the source are not the same kind of resource".to_string(),
+        ),
+    }
+}
+
+// Differences that won't crash, but probably break the display.
+fn check_layout(source: &str, translation: &str, report: &mut impl FnMut(Severity, String)) {
+    let expected = line_breaks(source);
+    let actual = line_breaks(translation);
+    if expected != actual {
+        report(
+            Severity::Warning,
+            format!("{actual} line break(s), the source has {expected}"),
+        );
+    }
+
+    let expected = markup(source);
+    let actual = markup(translation);
+    if expected != actual {
+        report(
+            Severity::Warning,
+            format!(
+                "markup [{}] doesn't match the source [{}]",
+                actual.join(", "),
+                expected.join(", ")
+            ),
+        );
+    }
+
+    let markup_regex = markup_regex();
+    let starts_with_markup = |value: &str| markup_regex.find(value).is_some_and(|m| m.start() == 0);
+    let ends_with_markup = |value: &str| {
+        markup_regex
+            .find_iter(value)
+            .last()
+            .is_some_and(|m| m.end() == value.len())
+    };
+    if starts_with_markup(source) != starts_with_markup(translation)
+        || ends_with_markup(source) != ends_with_markup(translation)
+    {
+        report(
+            Severity::Warning,
+            "leading or trailing markup differs from the source".to_string(),
+        );
+    }
+}
+
+// The placeholders as (position, conversion), with the implicit positions made explicit so that
+// `%s` and `%1$s` are considered the same.
+fn placeholders(value: &str) -> BTreeSet<(usize, char)> {
+    let mut next_position = 1;
+    placeholder_regex()
+        .captures_iter(value)
+        .filter_map(|captures| {
+            // `20 % de charge` is a percentage followed by a word, not `% d` with a space flag.
+            let followed_by_word = value[captures.get(0)?.end()..]
+                .chars()
+                .next()
+                .is_some_and(char::is_alphanumeric);
+            if captures[2].contains(' ') && followed_by_word {
+                return None;
+            }
+            let conversion = captures[3].chars().next()?;
+            if conversion == '%' || conversion == 'n' {
+                return None;
+            }
+            let position = match captures.get(1) {
+                Some(position) => position.as_str().parse().ok()?,
+                None => {
+                    next_position += 1;
+                    next_position - 1
+                }
+            };
+            Some((position, conversion.to_ascii_lowercase()))
+        })
+        .collect()
+}
+
+fn describe(placeholders: &BTreeSet<(usize, char)>) -> String {
+    let placeholders: Vec<_> = placeholders
+        .iter()
+        .map(|(position, conversion)| format!("%{position}${conversion}"))
+        .collect();
+    format!("[{}]", placeholders.join(", "))
+}
+
+fn line_breaks(value: &str) -> usize {
+    value.matches('\n').count()
+}
+
+fn markup(value: &str) -> Vec<String> {
+    markup_regex()
+        .captures_iter(value)
+        .map(|captures| format!("<{}{}>", &captures[1], &captures[2]))
+        .collect()
+}
+
+fn placeholder_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(PLACEHOLDER_REGEX).expect("Invalid placeholder regex"))
+}
+
+fn markup_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(MARKUP_REGEX).expect("Invalid markup regex"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn issues(source: &str, translation: &str) -> Vec<(Severity, String)> {
+        let mut issues = Vec::new();
+        check_layout(source, translation, &mut |severity, message| {
+            issues.push((severity, message))
+        });
+        issues
+    }
+
+    #[test]
+    fn placeholders_make_positions_explicit() {
+        assert_eq!(
+            placeholders("%s has %d GIFs"),
+            BTreeSet::from([(1, 's'), (2, 'd')])
+        );
+        assert_eq!(
+            placeholders("%2$d GIFs for %1$s"),
+            BTreeSet::from([(1, 's'), (2, 'd')])
+        );
+    }
+
+    #[test]
+    fn placeholders_read_flags_width_and_precision() {
+        assert_eq!(
+            placeholders("%1$-10.2f and %,d and %08X"),
+            BTreeSet::from([(1, 'f'), (1, 'd'), (2, 'x')])
+        );
+        assert_eq!(placeholders("%1$tY"), BTreeSet::from([(1, 't')]));
+    }
+
+    #[test]
+    fn placeholders_skip_escapes_and_line_separators() {
+        assert!(placeholders("100%% and %n").is_empty());
+    }
+
+    #[test]
+    fn placeholders_skip_percentages() {
+        assert!(placeholders("20 % de charge").is_empty());
+        assert!(placeholders("Remise de 20 % sur tout").is_empty());
+        assert!(placeholders("100 %").is_empty());
+        assert!(placeholders("50 %!").is_empty());
+    }
+
+    #[test]
+    fn placeholders_skip_unknown_conversions() {
+        assert!(placeholders("%y %k %Q").is_empty());
+    }
+
+    #[test]
+    fn placeholders_keep_a_space_flag_before_punctuation() {
+        assert_eq!(placeholders("% d."), BTreeSet::from([(1, 'd')]));
+    }
+
+    #[test]
+    fn markup_lists_the_tags_in_order() {
+        assert_eq!(
+            markup("<b>Bold</b> and <a href=\"https://example.com\">link</a>"),
+            ["<b>", "</b>", "<a>", "</a>"]
+        );
+        assert!(markup("1 < 2 > 0").is_empty());
+    }
+
+    #[test]
+    fn check_layout_accepts_the_same_layout() {
+        assert!(issues("<b>Open</b>\nnow", "<b>Ouvrir</b>\nmaintenant").is_empty());
+    }
+
+    #[test]
+    fn check_layout_warns_about_line_breaks() {
+        assert_eq!(
+            issues("One\nTwo", "Un deux"),
+            [(
+                Severity::Warning,
+                "0 line break(s), the source has 1".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn check_layout_warns_about_markup() {
+        assert_eq!(
+            issues("Tap <b>Open</b> now", "Touchez Ouvrir <i>maintenant</i>"),
+            [
+                (
+                    Severity::Warning,
+                    "markup [<i>, </i>] doesn't match the source [<b>, </b>]".to_string()
+                ),
+                (
+                    Severity::Warning,
+                    "leading or trailing markup differs from the source".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn check_layout_warns_about_leading_markup() {
+        assert_eq!(
+            issues("<b>Open</b> the GIF", "Ouvrez le GIF <b>maintenant</b>"),
+            [(
+                Severity::Warning,
+                "leading or trailing markup differs from the source".to_string()
+            )]
+        );
+    }
+}
@@ -0,0 +1,198 @@
+use anyhow::Result;
+use git2::{Commit, Oid};
+use regex::Regex;
+use semver::Version;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::message::Conventional;
+use crate::version::Bump;
+use crate::{Repo, Tag};
+
+// The line `git revert` adds to the message.
+const REVERT_REGEX: &str = r"This reverts commit ([0-9a-f]{7,40})";
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct BumpSettings {
+    // Only the commits touching one of these paths bump the version, all of them if empty.
+    pub paths: Vec<String>,
+}
+
+pub struct BumpingCommit {
+    pub summary: String,
+    pub bump: Bump,
+}
+
+pub struct ReleaseAs {
+    pub summary: String,
+    pub version: Version,
+}
+
+pub struct IgnoredCommit {
+    pub summary: String,
+    pub reason: String,
+}
+
+// The commits since the last release that bump the version, and the ones that would have but
+// were ignored, newest first.
+#[derive(Default)]
+pub struct BumpAnalysis {
+    pub bumping: Vec<BumpingCommit>,
+    pub ignored: Vec<IgnoredCommit>,
+    // The newest `Release-As` footer, it forces the next version.
+    pub release_as: Option<ReleaseAs>,
+    pub count: usize,
+}
+
+impl BumpAnalysis {
+    pub fn bump(&self) -> Option<Bump> {
+        self.bumping.iter().map(|commit| commit.bump).max()
+    }
+
+    // The oldest commit requiring the bump.
+    pub fn reason(&self) -> Option<&BumpingCommit> {
+        let bump = self.bump()?;
+        self.bumping.iter().rev().find(|commit| commit.bump == bump)
+    }
+}
+
+struct Walked<'r> {
+    commit: Commit<'r>,
+    summary: String,
+    conventional: Result<Conventional>,
+}
+
+// The bump required by the conventional commits since the last final release: prereleases
+// don't count, so that a promoted version is bumped from the previous final one. A reverted
+// commit doesn't count, nor does its revert, and with a path filter, only the commits touching
+// these paths count.
+pub fn analyze(repo: &Repo, last_release: &Tag, settings: &BumpSettings) -> Result<BumpAnalysis> {
+    let mut walked = Vec::new();
+    for oid in repo.walker(None, Some(last_release))? {
+        let commit = repo.repository.find_commit(oid?)?;
+        let message = String::from_utf8_lossy(commit.message_bytes());
+        walked.push(Walked {
+            summary: commit.summary().unwrap_or_default().to_string(),
+            conventional: Conventional::parse(&message),
+            commit,
+        });
+    }
+
+    let revert_regex = Regex::new(REVERT_REGEX)?;
+    let mut analysis = BumpAnalysis {
+        count: walked.len(),
+        ..Default::default()
+    };
+    // Reverted commits, with the summary of their revert. A revert that is itself reverted
+    // doesn't cancel anything, as the newest commits come first.
+    let mut reverted: HashMap<Oid, String> = HashMap::new();
+    for (index, walked_commit) in walked.iter().enumerate() {
+        let id = walked_commit.commit.id();
+        let summary = walked_commit.summary.clone();
+
+        if let Some(revert) = reverted.get(&id) {
+            analysis.ignored.push(IgnoredCommit {
+                summary,
+                reason: format!("reverted by \"{revert}\""),
+            });
+            continue;
+        }
+
+        if let Some(target) =
+            revert_target(&walked_commit.commit, &walked[index + 1..], &revert_regex)
+        {
+            reverted.insert(target.commit.id(), summary.clone());
+            analysis.ignored.push(IgnoredCommit {
+                summary,
+                reason: format!("reverts \"{}\"", target.summary),
+            });
+            continue;
+        }
+
+        let conventional = match &walked_commit.conventional {
+            Ok(conventional) => conventional,
+            Err(error) => {
+                analysis.ignored.push(IgnoredCommit {
+                    summary,
+                    reason: format!("not a conventional commit ({error})"),
+                });
+                continue;
+            }
+        };
+        if analysis.release_as.is_none() {
+            if let Some(version) = conventional.release_as()? {
+                analysis.release_as = Some(ReleaseAs {
+                    summary: summary.clone(),
+                    version,
+                });
+            }
+        }
+
+        let Some(bump) = conventional.bump() else {
+            continue;
+        };
+        if !settings.paths.is_empty() && !touches(repo, &walked_commit.commit, &settings.paths)? {
+            analysis.ignored.push(IgnoredCommit {
+                summary,
+                reason: format!("doesn't touch {}", settings.paths.join(", ")),
+            });
+            continue;
+        }
+
+        analysis.bumping.push(BumpingCommit { summary, bump });
+    }
+
+    Ok(analysis)
+}
+
+// The older commit a revert cancels, found from the "This reverts commit <sha>." line git adds,
+// or else from the reverted summary: `Revert "feat: x"` or `revert: feat: x`.
+fn revert_target<'a, 'r>(
+    commit: &Commit,
+    older: &'a [Walked<'r>],
+    revert_regex: &Regex,
+) -> Option<&'a Walked<'r>> {
+    let message = commit.message().unwrap_or_default();
+    let summary = commit.summary().unwrap_or_default();
+
+    let reverted_id = revert_regex
+        .captures(message)
+        .map(|captures| captures[1].to_string());
+    if let Some(reverted_id) = reverted_id {
+        return older
+            .iter()
+            .find(|walked| walked.commit.id().to_string().starts_with(&reverted_id));
+    }
+
+    let reverted_summary = summary
+        .strip_prefix("Revert \"")
+        .and_then(|rest| rest.strip_suffix('"'))
+        .or_else(|| summary.strip_prefix("revert: "))?;
+    older
+        .iter()
+        .find(|walked| walked.summary == reverted_summary)
+}
+
+fn touches(repo: &Repo, commit: &Commit, paths: &[String]) -> Result<bool> {
+    let tree = commit.tree()?;
+    let parent_tree = match commit.parent(0) {
+        Ok(parent) => Some(parent.tree()?),
+        Err(_) => None,
+    };
+    let diff = repo
+        .repository
+        .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)?;
+
+    Ok(diff.deltas().any(|delta| {
+        [delta.old_file().path(), delta.new_file().path()]
+            .into_iter()
+            .flatten()
+            .any(|changed| {
+                paths
+                    .iter()
+                    .any(|path| changed.starts_with(Path::new(path)))
+            })
+    }))
+}
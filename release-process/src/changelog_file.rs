@@ -0,0 +1,88 @@
+use anyhow::{anyhow, Result};
+use git_cliff_core::config::{ChangelogConfig, Config};
+use semver::Version;
+
+// The configuration rendering the release sections only, without the header and footer of the
+// file.
+pub fn sections_config(config: &Config) -> Config {
+    Config {
+        changelog: ChangelogConfig {
+            header: None,
+            footer: None,
+            ..config.changelog.clone()
+        },
+        ..config.clone()
+    }
+}
+
+// The changelog with the section of the next release inserted below its header, or above the
+// newest release when the header was edited. The rest is kept as is, manual edits included.
+pub fn insert_section(
+    existing: &str,
+    header: &str,
+    version: &Version,
+    section: &str,
+) -> Result<String> {
+    let heading = format!("[{version}]");
+    if existing
+        .lines()
+        .any(|line| line.starts_with('#') && line.contains(&heading))
+    {
+        return Err(anyhow!(
+            "CHANGELOG.md already has a section for {version}, restore it with `git checkout CHANGELOG.md`"
+        ));
+    }
+
+    let (before, after) = match existing.strip_prefix(header) {
+        Some(rest) if !header.is_empty() => (header, rest),
+        _ => {
+            let newest = existing
+                .match_indices("\n## ")
+                .next()
+                .map(|(index, _)| index + 1)
+                .unwrap_or(existing.len());
+            existing.split_at(newest)
+        }
+    };
+
+    Ok(format!("{before}{section}{after}"))
+}
+
+// Where the changelog differs from a full regeneration, if it does, with the line numbers of
+// the updated file.
+pub fn divergence(updated: &str, regenerated: &str) -> Option<String> {
+    if updated == regenerated {
+        return None;
+    }
+
+    let updated_lines: Vec<&str> = updated.lines().collect();
+    let regenerated_lines: Vec<&str> = regenerated.lines().collect();
+    let first = updated_lines
+        .iter()
+        .zip(&regenerated_lines)
+        .position(|(updated, regenerated)| updated != regenerated)
+        .unwrap_or(updated_lines.len().min(regenerated_lines.len()));
+
+    Some(format!(
+        "line {}: \"{}\" where a full regeneration has \"{}\" ({} lines against {})",
+        first + 1,
+        updated_lines.get(first).unwrap_or(&""),
+        regenerated_lines.get(first).unwrap_or(&""),
+        updated_lines.len(),
+        regenerated_lines.len()
+    ))
+}
+
+// The heading of the newest release section, like `## [2.3.0] - 2024-05-01`.
+pub fn newest_heading(changelog: &str) -> Option<&str> {
+    changelog.lines().find(|line| line.starts_with("## ["))
+}
+
+// The section of a release without its heading, as the body of a GitHub release or of a tag.
+pub fn release_notes(section: &str) -> String {
+    let body = match section.split_once('\n') {
+        Some((heading, body)) if heading.starts_with("## ") => body,
+        _ => section,
+    };
+    format!("{}\n", body.trim())
+}
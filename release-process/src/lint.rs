@@ -0,0 +1,231 @@
+use anyhow::{anyhow, Result};
+use git_cliff_core::commit::Commit;
+use git_cliff_core::config::Config;
+use git_cliff_core::error::Error as CliffError;
+use std::path::Path;
+
+use crate::message::{self, Conventional};
+use crate::Repo;
+
+// Prefixes of messages that never reach the main branch as is.
+const TRANSIENT_PREFIXES: [&str; 4] = ["fixup! ", "squash! ", "amend! ", "Merge "];
+
+// The changelog configurations a commit goes through, with their name.
+pub type Changelogs<'a> = [(&'a str, &'a Config)];
+
+#[derive(Default)]
+struct Report {
+    errors: Vec<String>,
+    notes: Vec<String>,
+}
+
+// Lints the commits of a range like `origin/main..HEAD`, or since the last release. Merge
+// commits are skipped, they are generated.
+pub fn lint_range(repo: &Repo, range: Option<&str>, changelogs: &Changelogs) -> Result<()> {
+    let mut revwalk = repo.repository.revwalk()?;
+    match range {
+        Some(range) if range.contains("..") => revwalk.push_range(range)?,
+        Some(revision) => {
+            revwalk.push(
+                repo.repository
+                    .revparse_single(revision)?
+                    .peel_to_commit()?
+                    .id(),
+            )?;
+            hide_last_release(repo, &mut revwalk)?;
+        }
+        None => {
+            revwalk.push_head()?;
+            hide_last_release(repo, &mut revwalk)?;
+        }
+    }
+
+    let mut checked = 0;
+    let mut failed = 0;
+    for oid in revwalk {
+        let commit = repo.repository.find_commit(oid?)?;
+        if commit.parent_count() > 1 {
+            continue;
+        }
+        checked += 1;
+
+        let message = String::from_utf8_lossy(commit.message_bytes());
+        let report = check(&commit.id().to_string(), &message, changelogs);
+        if report.errors.is_empty() && report.notes.is_empty() {
+            continue;
+        }
+        println!(
+            "{} {}",
+            &commit.id().to_string()[..7],
+            commit.summary().unwrap_or_default()
+        );
+        print(&report);
+        if !report.errors.is_empty() {
+            failed += 1;
+        }
+    }
+
+    println!("Checked {checked} commit(s), {failed} with errors");
+    if failed > 0 {
+        return Err(anyhow!(
+            "{failed} commit(s) don't follow https://www.conventionalcommits.org, reword them with `git rebase -i`"
+        ));
+    }
+
+    Ok(())
+}
+
+// Lints the message of a commit being written, for a commit-msg hook.
+pub fn lint_message_file(path: &Path, changelogs: &Changelogs) -> Result<()> {
+    let content = std::fs::read_to_string(path)?;
+    let message = strip_comments(&content);
+    if message.trim().is_empty()
+        || TRANSIENT_PREFIXES
+            .iter()
+            .any(|prefix| message.starts_with(prefix))
+    {
+        return Ok(());
+    }
+
+    let report = check("0000000", &message, changelogs);
+    print(&report);
+    if !report.errors.is_empty() {
+        return Err(anyhow!(
+            "The commit message doesn't follow https://www.conventionalcommits.org, like `feat: add a color picker`"
+        ));
+    }
+
+    Ok(())
+}
+
+fn check(id: &str, message: &str, changelogs: &Changelogs) -> Report {
+    let mut report = Report::default();
+    if reverts_conventional(message) == Some(true) {
+        report.notes.push(
+            "a revert, it cancels the reverted commit and is dropped from the changelogs"
+                .to_string(),
+        );
+        return report;
+    }
+    match Conventional::parse(message) {
+        Ok(conventional) => {
+            if let Err(error) = conventional.release_as() {
+                report.errors.push(error.to_string());
+            }
+        }
+        Err(error) => {
+            report.errors.push(format!(
+                "not a conventional commit ({error}), it doesn't bump the version and is dropped from the changelogs"
+            ));
+            return report;
+        }
+    }
+
+    for (name, config) in changelogs {
+        match Commit::new(id.to_string(), message::normalize(message)).process(&config.git) {
+            Ok(_) => {}
+            Err(CliffError::GroupError(_)) => report.notes.push(format!("skipped from {name}")),
+            Err(error) => report.errors.push(format!("dropped from {name}: {error}")),
+        }
+    }
+
+    report
+}
+
+// Whether the message is the `Revert "<summary>"` of `git revert` reverting a conventional
+// commit, or a revert of such a revert. None if it isn't a revert.
+fn reverts_conventional(message: &str) -> Option<bool> {
+    let reverted = message
+        .lines()
+        .next()?
+        .strip_prefix("Revert \"")?
+        .strip_suffix('"')?;
+    Some(reverts_conventional(reverted).unwrap_or_else(|| Conventional::parse(reverted).is_ok()))
+}
+
+fn print(report: &Report) {
+    for error in &report.errors {
+        println!("    error: {error}");
+    }
+    for note in &report.notes {
+        println!("    note: {note}");
+    }
+}
+
+fn hide_last_release(repo: &Repo, revwalk: &mut git2::Revwalk) -> Result<()> {
+    if let Some(last_release) = repo.last_release() {
+        revwalk.hide(last_release.commit_id)?;
+    }
+    Ok(())
+}
+
+// Removes what git removes from a message file before committing: comment lines, and everything
+// below the scissors line of `git commit --verbose`.
+fn strip_comments(content: &str) -> String {
+    content
+        .lines()
+        .take_while(|line| {
+            !line.starts_with("# ------------------------ >8 ------------------------")
+        })
+        .filter(|line| !line.starts_with('#'))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conventional_commits_pass() {
+        let report = check("0000000", "feat(ui): add a color picker", &[]);
+
+        assert!(report.errors.is_empty());
+        assert!(report.notes.is_empty());
+    }
+
+    #[test]
+    fn other_commits_fail() {
+        let report = check("0000000", "Add a color picker", &[]);
+
+        assert_eq!(report.errors.len(), 1);
+    }
+
+    #[test]
+    fn reverts_of_conventional_commits_pass() {
+        let message = "Revert \"feat(ui): add a color picker\"\n\nThis reverts commit 1234567.\n";
+        let report = check("0000000", message, &[]);
+
+        assert!(report.errors.is_empty());
+        assert_eq!(report.notes.len(), 1);
+    }
+
+    #[test]
+    fn reverts_of_reverts_pass() {
+        let message = "Revert \"Revert \"fix: crop the preview\"\"";
+        let report = check("0000000", message, &[]);
+
+        assert!(report.errors.is_empty());
+    }
+
+    #[test]
+    fn reverts_of_other_commits_fail() {
+        let report = check("0000000", "Revert \"Add a color picker\"", &[]);
+
+        assert_eq!(report.errors.len(), 1);
+    }
+
+    #[test]
+    fn bad_release_as_fails() {
+        let report = check("0000000", "fix: crop the preview\n\nRelease-As: soon", &[]);
+
+        assert_eq!(report.errors.len(), 1);
+    }
+
+    #[test]
+    fn strip_comments_drops_comments_and_the_diff() {
+        let content = "feat: add a color picker\n# Please enter the commit message\n# ------------------------ >8 ------------------------\ndiff --git a/x b/x\n";
+
+        assert_eq!(strip_comments(content), "feat: add a color picker");
+    }
+}
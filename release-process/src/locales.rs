@@ -0,0 +1,132 @@
+use anyhow::{anyhow, Context, Result};
+use semver::Version;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+const SOURCE_LANGUAGE: &str = "en";
+
+// A language of fetch-translations/config.json. Its store listing file name, like en-US.json, is
+// also the fastlane metadata directory that update-fastlane writes.
+#[derive(Deserialize, Debug)]
+pub struct Locale {
+    code: String,
+    store_listing_file: String,
+}
+
+// The release note of a locale, and whether it was translated.
+pub struct LocalizedNote {
+    pub path: PathBuf,
+    pub note: String,
+    pub translated: bool,
+}
+
+impl Locale {
+    pub fn load_all(root: &Path) -> Result<Vec<Locale>> {
+        let config_file = root.join("fetch-translations/config.json");
+        let locales: Vec<Locale> = serde_json::from_reader(
+            File::open(&config_file)
+                .with_context(|| format!("Couldn't open {}", config_file.display()))?,
+        )?;
+        if !locales.iter().any(|locale| locale.code == SOURCE_LANGUAGE) {
+            return Err(anyhow!(
+                "{} doesn't contain the {SOURCE_LANGUAGE} language",
+                config_file.display()
+            ));
+        }
+
+        Ok(locales)
+    }
+
+    pub fn fastlane_directory(&self) -> &str {
+        self.store_listing_file.trim_end_matches(".json")
+    }
+
+    // Relative to the root of the repository.
+    pub fn changelog_path(&self, version_code: u64) -> PathBuf {
+        PathBuf::from("fastlane/metadata/android")
+            .join(self.fastlane_directory())
+            .join("changelogs")
+            .join(format!("{version_code}.txt"))
+    }
+
+    // The note translated through fetch-translations, in the store listing.
+    fn release_note(&self, root: &Path, version: &Version) -> Result<Option<String>> {
+        let listing_file = root.join("store-listing").join(&self.store_listing_file);
+        if !listing_file.exists() {
+            return Ok(None);
+        }
+        let mut listing: HashMap<String, serde_json::Value> =
+            serde_json::from_reader(File::open(&listing_file)?)
+                .with_context(|| format!("Couldn't parse {}", listing_file.display()))?;
+
+        Ok(listing
+            .remove(&release_note_key(version))
+            .and_then(|note| note.as_str().map(str::trim).map(String::from))
+            .filter(|note| !note.is_empty()))
+    }
+}
+
+// The store listing key of the release note of a version, like release_notes_2_3_0. Prereleases
+// share the key of their final version.
+pub fn release_note_key(version: &Version) -> String {
+    format!(
+        "release_notes_{}_{}_{}",
+        version.major, version.minor, version.patch
+    )
+}
+
+// The note of each locale: the translated one if there is one, else the English one. The English
+// note is the one of the store listing if it was written by hand, else the generated one.
+pub fn localized_notes(
+    locales: &[Locale],
+    root: &Path,
+    version: &Version,
+    version_code: u64,
+    generated_note: &str,
+    max_length: usize,
+) -> Result<Vec<LocalizedNote>> {
+    let mut notes = Vec::new();
+    for locale in locales {
+        let note = locale.release_note(root, version)?.and_then(|note| {
+            let length = note.chars().count();
+            if length > max_length {
+                eprintln!(
+                    "{}: the release note of store-listing/{} is {length} characters long, more than {max_length}, it is ignored",
+                    locale.fastlane_directory(),
+                    locale.store_listing_file
+                );
+                None
+            } else {
+                Some(format!("{note}\n"))
+            }
+        });
+        notes.push((locale, note));
+    }
+
+    let english_note = notes
+        .iter()
+        .find(|(locale, _)| locale.code == SOURCE_LANGUAGE)
+        .and_then(|(_, note)| note.clone())
+        .unwrap_or_else(|| generated_note.to_string());
+
+    Ok(notes
+        .into_iter()
+        .map(|(locale, note)| {
+            if note.is_none() && locale.code != SOURCE_LANGUAGE {
+                info!(
+                    "{}: no {} in store-listing/{}, using the English release note",
+                    locale.fastlane_directory(),
+                    release_note_key(version),
+                    locale.store_listing_file
+                );
+            }
+            LocalizedNote {
+                path: locale.changelog_path(version_code),
+                translated: note.is_some() && locale.code != SOURCE_LANGUAGE,
+                note: note.unwrap_or_else(|| english_note.clone()),
+            }
+        })
+        .collect())
+}
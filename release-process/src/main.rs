@@ -1,239 +1,491 @@
 use anyhow::anyhow;
 use anyhow::Context;
 use anyhow::Result;
+use bump::BumpSettings;
+use clap::{Parser, Subcommand};
 use git2::Oid;
 use git2::Repository;
 use git2::Revwalk;
 use git_cliff_core::changelog::Changelog;
 use git_cliff_core::commit::Commit;
 use git_cliff_core::config::Config;
-use git_conventional::Type;
-use regex::Captures;
-use regex::Regex;
-use std::fmt::Display;
-use std::fs::File;
-use std::io::Read;
-use std::io::Write;
-use std::path::PathBuf;
+use locales::Locale;
+use plan::{BumpReason, Plan};
+use semver::Version;
+use settings::Settings;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use version::{Bump, VersionExt};
+use version_source::VersionFields;
 
-const PROJECT_DIR: &str = env!("CARGO_MANIFEST_DIR");
-const VERSION_NAME_REGEX: &str = r#"(versionName ")([0-9]+\.[0-9]+\.[0-9]+)(")"#;
-const VERSION_CODE_REGEX: &str = r#"(versionCode )([0-9]+)"#;
-
-#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
-struct Version {
-    major: u64,
-    minor: u64,
-    patch: u64,
-}
+// Set by `--output json`: the progress messages then go to stderr, and stdout is the plan only.
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
 
-impl Version {
-    fn from_tag(tag: &str) -> Result<Self> {
-        let parts: Vec<_> = tag[1..].split('.').collect();
-        if parts.len() != 3 {
-            return Err(anyhow!("Wrong tag"));
+macro_rules! info {
+    ($($arg:tt)*) => {
+        if crate::JSON_OUTPUT.load(std::sync::atomic::Ordering::Relaxed) {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
         }
+    };
+}
 
-        let major = parts[0].parse()?;
-        let minor = parts[1].parse()?;
-        let patch = parts[2].parse()?;
-
-        Ok(Self {
-            major,
-            minor,
-            patch,
-        })
-    }
+mod bump;
+mod changelog_file;
+mod lint;
+mod locales;
+mod message;
+mod plan;
+mod preflight;
+mod release_notes;
+mod rollback;
+mod settings;
+mod unreleased;
+mod version;
+mod version_code;
+mod version_source;
 
-    fn as_tag(&self) -> String {
-        format!("v{self}")
-    }
+const PROJECT_DIR: &str = env!("CARGO_MANIFEST_DIR");
+// The notes of the release for `gh release create --notes-file`, at the root of the repository.
+// It is ignored by git.
+const RELEASE_NOTES_FILE: &str = "release-notes.md";
+
+#[derive(Parser, Debug)]
+#[command(about = "Compute the next version from the conventional commits, and cut a release")]
+struct Args {
+    #[command(subcommand)]
+    action: Option<Action>,
+    /// Cut a prerelease for the beta track, like `beta` or `rc`: v2.3.0-beta.1, then v2.3.0-beta.2...
+    #[arg(long, value_name = "IDENTIFIER", conflicts_with = "promote")]
+    prerelease: Option<String>,
+    /// Promote the last prerelease to a final version: v2.3.0-beta.2 becomes v2.3.0.
+    #[arg(long)]
+    promote: bool,
+    /// Bump this level instead of the one computed from the commits.
+    #[arg(long, value_enum, conflicts_with = "promote")]
+    bump: Option<Bump>,
+    /// Release this version, like 2.3.0 or v2.3.0-beta.1, instead of computing it.
+    #[arg(
+        long = "version",
+        value_name = "VERSION",
+        value_parser = parse_version,
+        conflicts_with_all = ["prerelease", "promote", "bump"]
+    )]
+    explicit_version: Option<Version>,
+    /// Print the next version, version code and changelogs without touching the tree.
+    #[arg(long)]
+    dry_run: bool,
+    /// Update the files but don't commit them, nor tag.
+    #[arg(long)]
+    no_commit: bool,
+    /// Commit but don't tag.
+    #[arg(long)]
+    no_tag: bool,
+    /// Regenerate CHANGELOG.md from every tag, dropping its manual edits, instead of inserting
+    /// the section of the next release.
+    #[arg(long)]
+    full_changelog: bool,
+    /// The branch releases are cut from.
+    #[arg(long, value_name = "BRANCH", default_value = "main")]
+    release_branch: String,
+    /// Print the plan of the release as JSON on stdout, for workflows, and the progress on stderr.
+    #[arg(long, value_enum, default_value = "text")]
+    output: Output,
+}
 
-    fn next_major(&self) -> Self {
-        Self {
-            major: self.major + 1,
-            minor: 0,
-            patch: 0,
-        }
-    }
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Output {
+    Text,
+    Json,
+}
 
-    fn next_minor(&self) -> Self {
-        Self {
-            major: self.major,
-            minor: self.minor + 1,
-            patch: 0,
-        }
-    }
+#[derive(Subcommand, Debug)]
+enum Action {
+    /// Undo a release that failed or that should not be pushed: delete its tag, drop its commit
+    /// and revert the changes it made. Refuses to run once the release commit is on a remote
+    /// branch, a tag pushed on its own can't be detected.
+    #[command(alias = "undo")]
+    Rollback {
+        /// Without a release commit, restore the release files even if they don't hold a new
+        /// version or changelog section.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Show the changes since the last release, and the bump they would cause.
+    Unreleased,
+    /// Check that commits follow the conventional commits and make it to the changelogs. Exits
+    /// with an error if one doesn't, for hooks and pull request checks.
+    Lint {
+        /// The commits to check, like `origin/main..HEAD`. Defaults to the commits since the last
+        /// release.
+        #[arg(conflicts_with = "message_file")]
+        range: Option<String>,
+        /// Check the message of a commit being written instead, for a commit-msg hook.
+        #[arg(long, value_name = "FILE")]
+        message_file: Option<PathBuf>,
+    },
+}
 
-    fn next_patch(&self) -> Self {
-        Self {
-            major: self.major,
-            minor: self.minor,
-            patch: self.patch + 1,
-        }
-    }
+fn parse_version(arg: &str) -> Result<Version> {
+    Version::from_arg(arg)
 }
 
-impl Display for Version {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_fmt(format_args!(
-            "{major}.{minor}.{patch}",
-            major = self.major,
-            minor = self.minor,
-            patch = self.patch
-        ))
-    }
+#[derive(Debug)]
+enum Channel {
+    Release,
+    Prerelease(String),
+    Promote,
 }
 
 fn main() -> Result<()> {
+    let args = Args::parse();
+    JSON_OUTPUT.store(args.output == Output::Json, Ordering::Relaxed);
+    let channel = match (args.prerelease, args.promote) {
+        (Some(identifier), _) => Channel::Prerelease(identifier),
+        (None, true) => Channel::Promote,
+        (None, false) => Channel::Release,
+    };
+
     let repository =
         Repository::open(PathBuf::from(PROJECT_DIR).join("..")).expect("Couldn't find git repo");
 
-    let repo = Repo::new(repository);
-    let last_version = last_version(&repo)?;
-
-    println!("Last version {last_version:?}");
-
-    if let Some(next_version) = next_version(&repo)? {
-        println!("Next version: {next_version:?}");
-        let next_version_code = next_version_code()?;
-
-        update_versions_in_build_gradle(&next_version, next_version_code)?;
-
-        let changelog_file = File::create(PathBuf::from(PROJECT_DIR).join("../CHANGELOG.md"))?;
-
-        let main_config = Config::parse(&PathBuf::from(PROJECT_DIR).join("main-cliff.toml"))?;
-        changelog(&repo, &next_version, false, &main_config, &changelog_file)?;
+    let repo = Repo::new(repository)?;
+    let settings = Settings::read(&PathBuf::from(PROJECT_DIR).join("release.toml"))?;
 
-        let fastlane_changelog_folder = PathBuf::from(PROJECT_DIR)
-            .join("..")
-            .join("fastlane/metadata/android/en-US/changelogs");
-        std::fs::create_dir_all(&fastlane_changelog_folder)?;
-        let fastlane_changelog_file =
-            fastlane_changelog_folder.join(format!("{next_version_code}.txt"));
+    let main_config = Config::parse(&PathBuf::from(PROJECT_DIR).join("main-cliff.toml"))?;
+    let fastlane_config = Config::parse(&PathBuf::from(PROJECT_DIR).join("fastlane-cliff.toml"))?;
 
-        let fastlane_config =
-            Config::parse(&PathBuf::from(PROJECT_DIR).join("fastlane-cliff.toml"))?;
-        changelog(
-            &repo,
-            &next_version,
-            true,
-            &fastlane_config,
-            File::create(fastlane_changelog_file)?,
-        )?;
-
-        create_commit(&repo, &next_version, next_version_code)?;
+    match args.action {
+        Some(Action::Rollback { force }) => return rollback::rollback(&repo, &settings, force),
+        Some(Action::Unreleased) => {
+            return unreleased::unreleased(&repo, &main_config, &settings.bump)
+        }
+        Some(Action::Lint {
+            range,
+            message_file,
+        }) => {
+            let changelogs = [
+                ("CHANGELOG.md", &main_config),
+                ("the fastlane release notes", &fastlane_config),
+            ];
+            return match message_file {
+                Some(message_file) => lint::lint_message_file(&message_file, &changelogs),
+                None => lint::lint_range(&repo, range.as_deref(), &changelogs),
+            };
+        }
+        None => {}
     }
 
-    Ok(())
-}
+    let last_version = last_version(&repo);
 
-fn last_version(repo: &Repo) -> Result<Option<Version>> {
-    repo.last_tag().map(|tag| tag.map(|tag| tag.version))
-}
+    match &last_version {
+        Some(last_version) => info!("Last version: {}", last_version.as_tag()),
+        None => info!("No version tagged yet"),
+    }
+    let mut plan = Plan {
+        last_version: last_version.as_ref().map(Version::as_tag),
+        dry_run: args.dry_run,
+        ..Default::default()
+    };
 
-fn next_version(repo: &Repo) -> Result<Option<Version>> {
-    let remove_line_break = Regex::new(r"(\w)\n(\w)")?;
-
-    if let Some(last_tag) = repo.last_tag()? {
-        let mut major_bump = false;
-        let mut minor_bump = false;
-        let mut patch_bump = false;
-        let walker = repo.walker(None, Some(&last_tag))?;
-
-        let mut count = 0;
-        for oid in walker {
-            let oid = oid?;
-            let commit = repo.repository.find_commit(oid)?;
-
-            let message = String::from_utf8_lossy(commit.message_bytes());
-            // git_conventional panics when string is like "hello\nyou" so let's sanitize it.
-            let message = remove_line_break.replace(&message, "$1 $2");
-            let parsed = git_conventional::Commit::parse(&message);
-
-            if let Ok(parsed) = parsed {
-                if parsed.breaking() {
-                    major_bump = true
-                } else if parsed.type_() == Type::FEAT {
-                    minor_bump = true
-                } else if parsed.type_() == Type::FIX {
-                    patch_bump = true
+    let next_version = match args.explicit_version {
+        Some(version) => {
+            if let Some(last_version) = &last_version {
+                if &version <= last_version {
+                    return Err(anyhow!(
+                        "{} is not greater than the last version {}",
+                        version.as_tag(),
+                        last_version.as_tag()
+                    ));
                 }
             }
-            count += 1;
+            Some((version, BumpReason::Explicit))
         }
+        None => next_version(&repo, &channel, args.bump, &settings.bump)?,
+    };
+    let Some((next_version, bump_reason)) = next_version else {
+        info!("Nothing to release");
+        return print_plan(args.output, &plan);
+    };
 
-        println!("Walked through {count} commits");
+    let root = PathBuf::from(PROJECT_DIR).join("..");
+    let version_fields = settings.version_source.locate_in(&root)?;
+
+    info!("Next version: {}", next_version.as_tag());
+    let next_version_code = settings
+        .version_code
+        .next_version_code(version_fields.version_code(&root)?, &next_version)?;
+    info!("Next version code: {next_version_code}");
+
+    let (main_changelog, main_changelog_section) = main_changelog(
+        &repo,
+        &root,
+        &next_version,
+        &main_config,
+        args.full_changelog,
+    )?;
+
+    let fastlane_changelog = release_notes::fastlane_note(
+        changelog(&repo, &next_version, true, &fastlane_config)?,
+        &settings.release_notes,
+    )?;
+
+    let locales = Locale::load_all(&root)?;
+    let notes = locales::localized_notes(
+        &locales,
+        &root,
+        &next_version,
+        next_version_code,
+        &fastlane_changelog,
+        settings.release_notes.max_length,
+    )?;
+
+    let released_paths = released_paths(
+        next_version_code,
+        &locales,
+        &version_fields,
+        main_changelog.is_some(),
+    );
+    let tag = !args.no_commit && !args.no_tag;
+    let problems = preflight::Checks {
+        repo: &repo,
+        root: &root,
+        release_branch: &args.release_branch,
+        next_version: &next_version,
+        next_version_code,
+        released_paths: &released_paths,
+        tag,
+        version_source: &settings.version_source,
+    }
+    .run()?;
+    for problem in &problems {
+        eprintln!("{problem}");
+    }
 
-        Ok(if major_bump {
-            Some(last_tag.version.next_major())
-        } else if minor_bump {
-            Some(last_tag.version.next_minor())
-        } else if patch_bump {
-            Some(last_tag.version.next_patch())
-        } else {
-            None
-        })
-    } else {
-        Ok(None)
+    plan.next_version = Some(next_version.as_tag());
+    plan.version_code = Some(next_version_code);
+    plan.bump = Some(bump_reason);
+    plan.tag = tag.then(|| next_version.as_tag());
+    plan.commits = plan::commits(&repo)?;
+    plan.files = released_paths.clone();
+    plan.problems = problems.iter().map(ToString::to_string).collect();
+
+    if !problems.is_empty() && !args.dry_run {
+        print_plan(args.output, &plan)?;
+        return Err(anyhow!(
+            "{} pre-flight check(s) failed, nothing was changed",
+            problems.len()
+        ));
     }
-}
 
-fn next_version_code() -> Result<u64> {
-    let app_gradle_file = PathBuf::from(PROJECT_DIR)
-        .join("..")
-        .join("app/build.gradle");
+    if args.dry_run {
+        match &main_changelog {
+            Some(_) => info!("\n--- CHANGELOG.md\n{main_changelog_section}"),
+            None => info!(
+                "\n--- {RELEASE_NOTES_FILE} (CHANGELOG.md is only updated by final releases)\n{main_changelog_section}"
+            ),
+        }
+        let mut english_printed = false;
+        for note in &notes {
+            if note.translated {
+                info!("--- {} (translated)\n{}", note.path.display(), note.note);
+            } else if !english_printed {
+                info!("--- {} (English)\n{}", note.path.display(), note.note);
+                english_printed = true;
+            } else {
+                info!("--- {} (English)", note.path.display());
+            }
+        }
+        return print_plan(args.output, &plan);
+    }
 
-    let version_code_regex = Regex::new(VERSION_CODE_REGEX)?;
-    let content = std::fs::read_to_string(app_gradle_file)?;
+    version_fields.write(&root, &next_version, next_version_code)?;
 
-    let next_version_code: u64 = (version_code_regex
-        .captures(&content)
-        .ok_or_else(|| anyhow!("Couldn't find version code"))?[2]
-        .parse::<u64>()?)
-        + 1;
+    if let Some(main_changelog) = &main_changelog {
+        std::fs::write(root.join("CHANGELOG.md"), main_changelog)?;
+    }
+    let release_notes = changelog_file::release_notes(&main_changelog_section);
+    std::fs::write(root.join(RELEASE_NOTES_FILE), &release_notes)?;
+    plan.release_notes = Some(PathBuf::from(RELEASE_NOTES_FILE));
+
+    for note in &notes {
+        let path = root.join(&note.path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, &note.note)?;
+    }
 
-    Ok(next_version_code)
+    if args.no_commit {
+        info!("Files updated, not committing");
+        return print_plan(args.output, &plan);
+    }
+    create_commit(
+        &repo,
+        &next_version,
+        &released_paths,
+        (!args.no_tag).then_some(release_notes.as_str()),
+    )?;
+    plan.committed = true;
+
+    print_plan(args.output, &plan)
 }
 
-fn update_versions_in_build_gradle(next_version: &Version, next_version_code: u64) -> Result<()> {
-    let app_gradle_file = PathBuf::from(PROJECT_DIR)
-        .join("..")
-        .join("app/build.gradle");
+fn print_plan(output: Output, plan: &Plan) -> Result<()> {
+    if output == Output::Json {
+        println!("{}", serde_json::to_string_pretty(plan)?);
+    }
+    Ok(())
+}
 
-    let mut content = String::new();
-    File::open(&app_gradle_file)?.read_to_string(&mut content)?;
+fn last_version(repo: &Repo) -> Option<Version> {
+    repo.last_tag().map(|tag| tag.version)
+}
 
-    let version_name_regex = Regex::new(VERSION_NAME_REGEX)?;
-    let version_code_regex = Regex::new(VERSION_CODE_REGEX)?;
+// `forced_bump` replaces the bump computed from the commits, and releases even if there is none.
+fn next_version(
+    repo: &Repo,
+    channel: &Channel,
+    forced_bump: Option<Bump>,
+    bump_settings: &BumpSettings,
+) -> Result<Option<(Version, BumpReason)>> {
+    let last_tag = repo.last_tag();
+
+    if let Channel::Promote = channel {
+        return match last_tag {
+            Some(tag) if tag.version.is_prerelease() => Ok(Some((
+                tag.version.promote(),
+                BumpReason::Promote {
+                    prerelease: tag.version.as_tag(),
+                },
+            ))),
+            Some(tag) => Err(anyhow!(
+                "Can't promote {}, it is not a prerelease",
+                tag.version.as_tag()
+            )),
+            None => Err(anyhow!("Can't promote, there is no tag yet")),
+        };
+    }
 
-    let content = version_name_regex.replace(&content, |caps: &Captures| {
-        format!("{}{}{}", &caps[1], next_version, &caps[3])
-    });
+    let Some(last_release) = repo.last_release() else {
+        return match forced_bump {
+            Some(_) => Err(anyhow!(
+                "There is no release to bump yet, set the first one with --version"
+            )),
+            None => Ok(None),
+        };
+    };
+    if let Some(last_tag) = &last_tag {
+        // Nothing happened since the last prerelease.
+        if forced_bump.is_none()
+            && last_tag.commit_id == repo.repository.head()?.peel_to_commit()?.id()
+        {
+            return Ok(None);
+        }
+    }
 
-    let content = version_code_regex.replace(&content, |caps: &Captures| {
-        format!("{}{}", &caps[1], next_version_code)
-    });
+    let (next_version, reason) = match forced_bump {
+        Some(bump) => (
+            last_release.version.bump(bump),
+            BumpReason::Forced { level: bump },
+        ),
+        None => {
+            let analysis = bump::analyze(repo, &last_release, bump_settings)?;
+            for ignored in &analysis.ignored {
+                info!("Ignoring \"{}\": {}", ignored.summary, ignored.reason);
+            }
+            info!("Walked through {} commits", analysis.count);
+            if let Some(release_as) = analysis.release_as {
+                if release_as.version <= last_release.version {
+                    return Err(anyhow!(
+                        "\"{}\" asks to release {}, which is not greater than {}",
+                        release_as.summary,
+                        release_as.version.as_tag(),
+                        last_release.version.as_tag()
+                    ));
+                }
+                info!(
+                    "Release as {}, from \"{}\"",
+                    release_as.version.as_tag(),
+                    release_as.summary
+                );
+                (
+                    release_as.version,
+                    BumpReason::ReleaseAs {
+                        summary: release_as.summary,
+                    },
+                )
+            } else {
+                match analysis.reason() {
+                    Some(reason) => {
+                        info!("{:?} bump, from \"{}\"", reason.bump, reason.summary);
+                        (
+                            last_release.version.bump(reason.bump),
+                            BumpReason::Commit {
+                                level: reason.bump,
+                                summary: reason.summary.clone(),
+                            },
+                        )
+                    }
+                    None => return Ok(None),
+                }
+            }
+        }
+    };
 
-    let mut output = File::create(app_gradle_file)?;
-    output.write_all(content.as_bytes())?;
+    let next_version = match channel {
+        Channel::Prerelease(identifier) => {
+            // Follow the highest prerelease with the same identifier, even if another one came
+            // after it: v2.3.0-beta.2 after v2.3.0-beta.1 and v2.3.0-rc.1.
+            let mut next_prerelease = next_version.next_prerelease(identifier, None)?;
+            for tag in repo.tags() {
+                next_prerelease = next_prerelease
+                    .max(next_version.next_prerelease(identifier, Some(&tag.version))?);
+            }
+            next_prerelease
+        }
+        _ => next_version,
+    };
 
-    Ok(())
+    Ok(Some((next_version, reason)))
 }
 
-fn create_commit(repo: &Repo, next_version: &Version, next_version_code: u64) -> Result<()> {
+// The files written by a release, relative to the root of the repository.
+fn released_paths(
+    next_version_code: u64,
+    locales: &[Locale],
+    version_fields: &VersionFields,
+    with_changelog: bool,
+) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    if with_changelog {
+        paths.push(PathBuf::from("CHANGELOG.md"));
+    }
+    paths.extend(version_fields.paths());
+    paths.extend(
+        locales
+            .iter()
+            .map(|locale| locale.changelog_path(next_version_code)),
+    );
+    paths
+}
+
+fn create_commit(
+    repo: &Repo,
+    next_version: &Version,
+    released_paths: &[PathBuf],
+    // The notes of the annotated tag, no tag if None.
+    tag_notes: Option<&str>,
+) -> Result<()> {
     // Read https://zsiciarz.github.io/24daysofrust/book/vol2/day16.html
     // and https://paritytech.github.io/substrate/master/git2/struct.Repository.html#method.signature
 
-    let repository = &repo.repository;
-
-    let mut index = repository.index()?;
-    index.add_path(&PathBuf::from("CHANGELOG.MD"))?;
-    index.add_path(&PathBuf::from("app/build.gradle"))?;
-    index.add_path(&PathBuf::from(format!(
-        "fastlane/metadata/android/en-US/changelogs/{next_version_code}.txt"
-    )))?;
+    let mut index = repo.repository.index()?;
+    for path in released_paths {
+        index.add_path(path)?;
+    }
     index.write()?;
 
     let message = format!("chore(release): {next_version}");
@@ -241,38 +493,96 @@ fn create_commit(repo: &Repo, next_version: &Version, next_version_code: u64) ->
     // We want to allow for signing the commit, and it's not trivial in pure rust.
     git(&["commit", "-m", &message])?;
 
-    let tag_message = format!("Version {next_version}");
-    git(&["tag", "-a", &next_version.as_tag(), "-m", &tag_message])?;
+    if let Some(tag_notes) = tag_notes {
+        let tag_message = format!("Version {next_version}\n\n{tag_notes}");
+        // The default cleanup would strip the markdown headings as comments.
+        git(&[
+            "tag",
+            "-a",
+            &next_version.as_tag(),
+            "--cleanup=whitespace",
+            "-m",
+            &tag_message,
+        ])?;
+    }
 
     Ok(())
 }
 
-fn changelog<W>(
+// The updated CHANGELOG.md, and the section of the next release. The section is inserted in the
+// existing file, unless a full regeneration is asked for or there is no file yet. Prereleases
+// don't update the file, their changes go in the section of the final release.
+fn main_changelog(
     repo: &Repo,
+    root: &Path,
     next_version: &Version,
-    only_next: bool,
     config: &Config,
-    into: W,
-) -> Result<()>
-where
-    W: Write,
-{
-    let mut into = into;
+    full: bool,
+) -> Result<(Option<String>, String)> {
+    let sections_config = changelog_file::sections_config(config);
+    let mut section = Vec::new();
+    changelog(repo, next_version, true, &sections_config)?.generate(&mut section)?;
+    let section = String::from_utf8(section)?;
+    if next_version.is_prerelease() {
+        return Ok((None, section));
+    }
+
+    let mut regenerated = Vec::new();
+    changelog(repo, next_version, false, config)?.generate(&mut regenerated)?;
+    let regenerated = String::from_utf8(regenerated)?;
+
+    let changelog_file = root.join("CHANGELOG.md");
+    if full || !changelog_file.exists() {
+        return Ok((Some(regenerated), section));
+    }
+
+    let updated = changelog_file::insert_section(
+        &std::fs::read_to_string(&changelog_file)?,
+        config.changelog.header.as_deref().unwrap_or_default(),
+        next_version,
+        &section,
+    )?;
+    if let Some(divergence) = changelog_file::divergence(&updated, &regenerated) {
+        eprintln!(
+            "CHANGELOG.md differs from a full regeneration, at {divergence}. The manual edits are kept, use --full-changelog to drop them"
+        );
+    }
+
+    Ok((Some(updated), section))
+}
+
+fn changelog<'a>(
+    repo: &Repo,
+    next_version: &Version,
+    only_next: bool,
+    config: &'a Config,
+) -> Result<Changelog<'a>> {
     let head_id = repo.repository.head()?.peel_to_commit()?.id();
-    let next_tag = Tag::new(*next_version, head_id);
+    let next_tag = Tag::new(next_version.clone(), head_id);
+    // Prereleases are folded in the release that follows them, each section lists the changes
+    // since the previous final release.
     let releases = if only_next {
-        vec![Release::new(next_tag.version, head_id, repo.last_tag()?)]
+        vec![Release::new(
+            Some(next_tag.version),
+            head_id,
+            repo.last_release(),
+        )]
     } else {
-        let mut tags = repo.tags()?;
+        let mut tags: Vec<Tag> = repo
+            .tags()
+            .iter()
+            .filter(|tag| !tag.version.is_prerelease())
+            .cloned()
+            .collect();
         tags.push(next_tag);
-        tags.sort_by(|a, b| a.version.cmp(&b.version));
+        tags.sort_by_key(|tag| tag.version.clone());
         tags.reverse();
 
         let mut releases: Vec<Release> = tags
             .windows(2)
             .map(|window| {
                 Release::new(
-                    window[0].version,
+                    Some(window[0].version.clone()),
                     window[0].commit_id,
                     Some(window[1].clone()),
                 )
@@ -280,7 +590,7 @@ where
             .collect();
 
         if let Some(last) = tags.pop() {
-            releases.push(Release::new(last.version, last.commit_id, None));
+            releases.push(Release::new(Some(last.version), last.commit_id, None));
         }
 
         releases
@@ -295,54 +605,80 @@ where
     });
     releases.reverse();
 
-    let changelog = Changelog::new(releases, config)?;
-
-    changelog.generate(&mut into)?;
-
-    Ok(())
+    Ok(Changelog::new(releases, config)?)
 }
 
 struct Repo {
     repository: Repository,
+    tags: Vec<Tag>,
 }
 
 impl Repo {
-    fn new(repository: Repository) -> Self {
-        Self { repository }
+    fn new(repository: Repository) -> Result<Self> {
+        let tags = Self::read_tags(&repository)?;
+        Ok(Self { repository, tags })
     }
 
-    fn tags(&self) -> Result<Vec<Tag>> {
+    // Tags that are not `v` followed by a semantic version are skipped.
+    fn read_tags(repository: &Repository) -> Result<Vec<Tag>> {
         let mut tags: Vec<Tag> = Vec::new();
-        let tag_names = self.repository.tag_names(None)?;
+        let tag_names = repository.tag_names(None)?;
         for tag_name in tag_names.iter().flatten().map(String::from) {
-            let obj = self.repository.revparse_single(&tag_name)?;
+            let version = match Version::from_tag(&tag_name) {
+                Ok(version) => version,
+                Err(error) => {
+                    info!("Ignoring tag {tag_name}: {error}");
+                    continue;
+                }
+            };
+            let obj = repository.revparse_single(&tag_name)?;
             if let Ok(commit) = obj.clone().into_commit() {
-                tags.push(Tag::new(Version::from_tag(&tag_name)?, commit.id()));
+                tags.push(Tag::new(version, commit.id()));
             } else if let Some(tag) = obj.as_tag() {
                 if let Some(commit) = tag
                     .target()
                     .ok()
                     .and_then(|target| target.into_commit().ok())
                 {
-                    tags.push(Tag::new(Version::from_tag(&tag_name)?, commit.id()));
+                    tags.push(Tag::new(version, commit.id()));
                 }
             }
         }
-        tags.sort_by(|a, b| a.version.cmp(&b.version));
+        tags.sort_by_key(|tag| tag.version.clone());
 
         Ok(tags)
     }
 
-    fn last_tag(&self) -> Result<Option<Tag>> {
-        let mut tags = self.tags()?;
-        Ok(tags.pop())
+    fn tags(&self) -> &[Tag] {
+        &self.tags
+    }
+
+    fn last_tag(&self) -> Option<Tag> {
+        self.tags.last().cloned()
     }
 
-    fn walker(&self, from: Option<&Tag>, to: Option<&Tag>) -> Result<Revwalk> {
+    fn last_release(&self) -> Option<Tag> {
+        self.tags
+            .iter()
+            .rfind(|tag| !tag.version.is_prerelease())
+            .cloned()
+    }
+
+    // The content of a file of the repository at a commit, if it exists there.
+    fn file_at(&self, commit_id: Oid, path: &Path) -> Result<Option<String>> {
+        let tree = self.repository.find_commit(commit_id)?.tree()?;
+        let Ok(entry) = tree.get_path(path) else {
+            return Ok(None);
+        };
+        let blob = entry.to_object(&self.repository)?.peel_to_blob()?;
+        Ok(Some(String::from_utf8_lossy(blob.content()).into_owned()))
+    }
+
+    fn walker(&self, from: Option<Oid>, to: Option<&Tag>) -> Result<Revwalk<'_>> {
         let mut revwalker = self.repository.revwalk()?;
         revwalker.simplify_first_parent()?;
         match from {
-            Some(tag) => revwalker.push(tag.commit_id),
+            Some(commit_id) => revwalker.push(commit_id),
             None => revwalker.push_head(),
         }?;
         if let Some(tag) = to {
@@ -365,21 +701,20 @@ impl Tag {
     }
 }
 
+// A release, or the unreleased changes when it has no version.
 struct Release {
-    version: Version,
+    version: Option<Version>,
     from: Oid,
     to: Option<Tag>,
 }
 
 impl Release {
-    fn new(version: Version, from: Oid, to: Option<Tag>) -> Self {
+    fn new(version: Option<Version>, from: Oid, to: Option<Tag>) -> Self {
         Self { version, from, to }
     }
 
-    fn as_cliff_release(&self, repo: &Repo) -> Result<git_cliff_core::release::Release> {
-        let remove_line_break = Regex::new(r"(\w)\n(\w)")?;
-
-        let walker = repo.walker(Some(&Tag::new(self.version, self.from)), self.to.as_ref())?;
+    fn as_cliff_release<'a>(&self, repo: &Repo) -> Result<git_cliff_core::release::Release<'a>> {
+        let walker = repo.walker(Some(self.from), self.to.as_ref())?;
         let tag_commit = repo.repository.find_commit(self.from)?;
 
         let mut commits: Vec<Commit> = walker
@@ -388,14 +723,12 @@ impl Release {
             .filter_map(|commit_id| repo.repository.find_commit(commit_id).ok())
             .map(|commit| {
                 let message = String::from_utf8_lossy(commit.message_bytes());
-                // git_conventional panics when string is like "hello\nyou" so let's sanitize it.
-                let message = remove_line_break.replace(&message, "$1 $2");
-                Commit::new(commit.id().to_string(), message.into_owned())
+                Commit::new(commit.id().to_string(), message::normalize(&message))
             })
             .collect();
         commits.reverse();
         Ok(git_cliff_core::release::Release {
-            version: Some(self.version.as_tag()),
+            version: self.version.as_ref().map(Version::as_tag),
             commits,
             commit_id: Some(self.from.to_string()),
             timestamp: tag_commit.time().seconds(),
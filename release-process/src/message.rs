@@ -0,0 +1,73 @@
+use anyhow::{anyhow, Result};
+use semver::Version;
+
+use crate::version::{Bump, VersionExt};
+
+const RELEASE_AS: &str = "Release-As";
+
+// What a conventional commit means for the release.
+pub struct Conventional {
+    pub type_: String,
+    pub scope: Option<String>,
+    pub breaking: bool,
+    // The raw value of a `Release-As: x.y.z` footer.
+    pub release_as: Option<String>,
+}
+
+impl Conventional {
+    pub fn parse(message: &str) -> Result<Self> {
+        let message = normalize(message);
+        let commit = git_conventional::Commit::parse(&message).map_err(|error| anyhow!(error))?;
+
+        Ok(Self {
+            type_: commit.type_().as_str().to_lowercase(),
+            scope: commit.scope().map(|scope| scope.as_str().to_string()),
+            breaking: commit.breaking(),
+            release_as: commit
+                .footers()
+                .iter()
+                .find(|footer| footer.token().as_str().eq_ignore_ascii_case(RELEASE_AS))
+                .map(|footer| footer.value().trim().to_string()),
+        })
+    }
+
+    pub fn bump(&self) -> Option<Bump> {
+        if self.breaking {
+            Some(Bump::Major)
+        } else if self.type_ == "feat" {
+            Some(Bump::Minor)
+        } else if self.type_ == "fix" {
+            Some(Bump::Patch)
+        } else {
+            None
+        }
+    }
+
+    pub fn release_as(&self) -> Result<Option<Version>> {
+        self.release_as
+            .as_deref()
+            .map(|value| {
+                Version::from_arg(value)
+                    .map_err(|error| anyhow!("Invalid {RELEASE_AS} footer \"{value}\": {error}"))
+            })
+            .transpose()
+    }
+}
+
+// Makes a message acceptable to the conventional commit parser without losing its body and
+// footers: line endings are unified, whitespace-only lines emptied, and a missing blank line
+// after the summary is added, as git itself shows a summary followed by a line as is.
+pub fn normalize(message: &str) -> String {
+    let mut lines: Vec<&str> = message
+        .lines()
+        .map(|line| if line.trim().is_empty() { "" } else { line })
+        .collect();
+    while lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+    if lines.len() > 1 && !lines[1].is_empty() {
+        lines.insert(1, "");
+    }
+
+    lines.join("\n")
+}
@@ -0,0 +1,77 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::path::PathBuf;
+
+use crate::message::Conventional;
+use crate::version::Bump;
+use crate::Repo;
+
+// What a release does, printed with `--output json` for the workflows.
+#[derive(Serialize, Default)]
+pub struct Plan {
+    pub last_version: Option<String>,
+    // None when there is nothing to release.
+    pub next_version: Option<String>,
+    pub version_code: Option<u64>,
+    pub bump: Option<BumpReason>,
+    // None with --no-commit or --no-tag.
+    pub tag: Option<String>,
+    pub commits: Vec<PlannedCommit>,
+    // Relative to the root of the repository.
+    pub files: Vec<PathBuf>,
+    // The release-notes.md artifact, once written.
+    pub release_notes: Option<PathBuf>,
+    pub problems: Vec<String>,
+    pub dry_run: bool,
+    pub committed: bool,
+}
+
+// Why the next version is what it is.
+#[derive(Serialize, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BumpReason {
+    // The highest bump of the commits, from the oldest commit requiring it.
+    Commit { level: Bump, summary: String },
+    // A `Release-As` footer.
+    ReleaseAs { summary: String },
+    // --bump.
+    Forced { level: Bump },
+    // --version.
+    Explicit,
+    // --promote.
+    Promote { prerelease: String },
+}
+
+// A commit since the last release, type and scope are None if it isn't conventional.
+#[derive(Serialize)]
+pub struct PlannedCommit {
+    pub id: String,
+    pub summary: String,
+    #[serde(rename = "type")]
+    pub type_: Option<String>,
+    pub scope: Option<String>,
+    pub breaking: bool,
+}
+
+// The commits since the last final release, newest first, like in the changelog section.
+pub fn commits(repo: &Repo) -> Result<Vec<PlannedCommit>> {
+    let mut commits = Vec::new();
+    for oid in repo.walker(None, repo.last_release().as_ref())? {
+        let commit = repo.repository.find_commit(oid?)?;
+        let conventional =
+            Conventional::parse(&String::from_utf8_lossy(commit.message_bytes())).ok();
+        commits.push(PlannedCommit {
+            id: commit.id().to_string(),
+            summary: commit.summary().unwrap_or_default().to_string(),
+            breaking: conventional
+                .as_ref()
+                .is_some_and(|conventional| conventional.breaking),
+            type_: conventional
+                .as_ref()
+                .map(|conventional| conventional.type_.clone()),
+            scope: conventional.and_then(|conventional| conventional.scope),
+        });
+    }
+
+    Ok(commits)
+}
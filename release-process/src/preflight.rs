@@ -0,0 +1,203 @@
+use anyhow::Result;
+use git2::{ErrorCode, Status, StatusOptions};
+use semver::Version;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::version::VersionExt;
+use crate::version_source::VersionSource;
+use crate::Repo;
+
+// Everything that would leave a half-written release behind, checked before touching the tree.
+pub struct Checks<'a> {
+    pub repo: &'a Repo,
+    pub root: &'a Path,
+    pub release_branch: &'a str,
+    pub next_version: &'a Version,
+    pub next_version_code: u64,
+    // The files the release writes, relative to the root.
+    pub released_paths: &'a [PathBuf],
+    pub tag: bool,
+    pub version_source: &'a VersionSource,
+}
+
+impl Checks<'_> {
+    // One actionable message per failed check.
+    pub fn run(&self) -> Result<Vec<String>> {
+        let mut problems = Vec::new();
+        problems.extend(self.dirty_files()?);
+        problems.extend(self.unwritable_files());
+        problems.extend(self.branch()?);
+        if self.tag {
+            problems.extend(self.existing_tag()?);
+        }
+        problems.extend(self.version_code()?);
+        problems.extend(self.tagged_version_code()?);
+        Ok(problems)
+    }
+
+    // The release files too: rolling back a release that stopped halfway restores them from HEAD.
+    fn dirty_files(&self) -> Result<Option<String>> {
+        let mut options = StatusOptions::new();
+        options.include_untracked(false).include_ignored(false);
+        let statuses = self.repo.repository.statuses(Some(&mut options))?;
+
+        let mut dirty: Vec<String> = statuses
+            .iter()
+            .filter(|entry| entry.status() != Status::CURRENT)
+            .filter_map(|entry| entry.path().map(String::from))
+            .collect();
+        // Untracked files the release would overwrite.
+        dirty.extend(
+            self.released_paths
+                .iter()
+                .filter(|path| {
+                    self.repo
+                        .repository
+                        .status_file(path)
+                        .is_ok_and(|status| status.contains(Status::WT_NEW))
+                })
+                .map(|path| path.display().to_string()),
+        );
+
+        Ok((!dirty.is_empty()).then(|| {
+            format!(
+                "The working tree has uncommitted changes, commit or stash them first, or run `release-process rollback` if a release stopped halfway: {}",
+                dirty.join(", ")
+            )
+        }))
+    }
+
+    // A released path that can't be written would stop the release halfway, with the version
+    // already bumped.
+    fn unwritable_files(&self) -> Option<String> {
+        let unwritable: Vec<String> = self
+            .released_paths
+            .iter()
+            .filter(|path| {
+                let path = self.root.join(path);
+                if path.exists() {
+                    return !path.is_file();
+                }
+                // Missing directories are created, unless a file is in the way.
+                !path
+                    .ancestors()
+                    .skip(1)
+                    .find(|ancestor| ancestor.exists())
+                    .is_some_and(Path::is_dir)
+            })
+            .map(|path| path.display().to_string())
+            .collect();
+
+        (!unwritable.is_empty()).then(|| {
+            format!(
+                "The release can't write {}, a directory or a file is in the way",
+                unwritable.join(", ")
+            )
+        })
+    }
+
+    fn branch(&self) -> Result<Option<String>> {
+        let head = match self.repo.repository.head() {
+            Ok(head) => head,
+            Err(error) if error.code() == ErrorCode::UnbornBranch => {
+                return Ok(Some("HEAD has no commit yet".to_string()))
+            }
+            Err(error) => return Err(error.into()),
+        };
+        if !head.is_branch() {
+            return Ok(Some(format!(
+                "HEAD is detached, run `git switch {}` first",
+                self.release_branch
+            )));
+        }
+
+        let branch = head.shorthand().unwrap_or_default();
+        Ok((branch != self.release_branch).then(|| {
+            format!(
+                "HEAD is on {branch}, releases are cut from {}: run `git switch {}` first, or pass --release-branch {branch}",
+                self.release_branch, self.release_branch
+            )
+        }))
+    }
+
+    fn existing_tag(&self) -> Result<Option<String>> {
+        let tag = self.next_version.as_tag();
+        let exists = self
+            .repo
+            .repository
+            .find_reference(&format!("refs/tags/{tag}"))
+            .is_ok();
+
+        Ok(exists.then(|| {
+            format!("Tag {tag} already exists, delete it with `git tag -d {tag}` or release another version with --version")
+        }))
+    }
+
+    // Fastlane picks the changelog by version code, an older code would never be shown.
+    fn version_code(&self) -> Result<Option<String>> {
+        let metadata = self.root.join("fastlane/metadata/android");
+        if !metadata.exists() {
+            return Ok(None);
+        }
+
+        let mut highest: Option<(u64, PathBuf)> = None;
+        for locale in fs::read_dir(&metadata)? {
+            let changelogs = locale?.path().join("changelogs");
+            if !changelogs.is_dir() {
+                continue;
+            }
+            for changelog in fs::read_dir(changelogs)? {
+                let path = changelog?.path();
+                let Some(code) = path
+                    .file_stem()
+                    .filter(|_| path.extension().is_some_and(|extension| extension == "txt"))
+                    .and_then(|stem| stem.to_str())
+                    .and_then(|stem| stem.parse::<u64>().ok())
+                else {
+                    continue;
+                };
+                if highest.as_ref().is_none_or(|(highest, _)| code > *highest) {
+                    highest = Some((code, path));
+                }
+            }
+        }
+
+        Ok(highest
+            .filter(|(code, _)| *code >= self.next_version_code)
+            .map(|(code, path)| {
+                format!(
+                    "The next version code {} is not greater than the one of {} ({code}), bump the versionCode",
+                    self.next_version_code,
+                    path.strip_prefix(self.root).unwrap_or(&path).display()
+                )
+            }))
+    }
+
+    // Whatever the strategy, the code must be greater than the one of every released version,
+    // read from the version source at its tag.
+    fn tagged_version_code(&self) -> Result<Option<String>> {
+        let mut highest: Option<(u64, String)> = None;
+        for tag in self.repo.tags() {
+            let code = self
+                .version_source
+                .version_code_with(|path| self.repo.file_at(tag.commit_id, path));
+            // Old tags may predate the current version source.
+            let Ok(code) = code else {
+                continue;
+            };
+            if highest.as_ref().is_none_or(|(highest, _)| code > *highest) {
+                highest = Some((code, tag.version.as_tag()));
+            }
+        }
+
+        Ok(highest
+            .filter(|(code, _)| *code >= self.next_version_code)
+            .map(|(code, tag)| {
+                format!(
+                    "The next version code {} is not greater than the one of {tag} ({code}), Play would refuse it: check the version code strategy in release-process/release.toml",
+                    self.next_version_code
+                )
+            }))
+    }
+}
@@ -0,0 +1,54 @@
+use anyhow::{anyhow, Result};
+use git_cliff_core::changelog::Changelog;
+
+use crate::settings::ReleaseNotes;
+
+// Renders the note of the next release for the Play Store. If it is too long, commits are
+// dropped by priority, and replaced by a "and N more" line, until it fits.
+pub fn fastlane_note(mut changelog: Changelog, policy: &ReleaseNotes) -> Result<String> {
+    let note = render(&changelog)?;
+    let length = note.chars().count();
+    if length <= policy.max_length {
+        return Ok(note);
+    }
+
+    let release = changelog
+        .releases
+        .first_mut()
+        .ok_or_else(|| anyhow!("The release note has no release"))?;
+    let mut commits = std::mem::take(&mut release.commits);
+    // The sort is stable, the commits of a group stay in the order of the template.
+    commits.sort_by_key(|commit| {
+        policy
+            .priority
+            .iter()
+            .position(|group| Some(group) == commit.group.as_ref())
+            .unwrap_or(policy.priority.len())
+    });
+
+    let total = commits.len();
+    for kept in (1..total).rev() {
+        changelog.releases[0].commits = commits[..kept].to_vec();
+        let more = policy.more.replace("{count}", &(total - kept).to_string());
+        let note = format!("{}\n{more}\n", render(&changelog)?.trim_end());
+        if note.chars().count() <= policy.max_length {
+            info!(
+                "The release note was {length} characters long, {} commit(s) were left out to fit in {}",
+                total - kept,
+                policy.max_length
+            );
+            return Ok(note);
+        }
+    }
+
+    Err(anyhow!(
+        "The release note is {length} characters long, it doesn't fit in {} even with a single commit: reword the first commits with a commit_preprocessors entry in fastlane-cliff.toml, or write the note by hand",
+        policy.max_length
+    ))
+}
+
+fn render(changelog: &Changelog) -> Result<String> {
+    let mut note = Vec::new();
+    changelog.generate(&mut note)?;
+    Ok(String::from_utf8(note)?)
+}
@@ -0,0 +1,358 @@
+use anyhow::{anyhow, Result};
+use git2::{ApplyLocation, BranchType, Commit, Oid, Repository, Status};
+use semver::Version;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::changelog_file;
+use crate::locales::Locale;
+use crate::settings::Settings;
+use crate::version::VersionExt;
+use crate::version_source::VersionFields;
+use crate::{git, released_paths, Repo, PROJECT_DIR};
+
+const RELEASE_COMMIT_PREFIX: &str = "chore(release): ";
+
+// The changes of a release commit are reverted, keeping the edits made since. Without a release
+// commit, the release files are only restored when they hold a release that failed before
+// committing, or with `force`: pre-flight refuses to release over uncommitted changes to them.
+pub fn rollback(repo: &Repo, settings: &Settings, force: bool) -> Result<()> {
+    let head = repo.repository.head()?.peel_to_commit()?;
+    let released_version = head
+        .summary()
+        .and_then(|summary| summary.strip_prefix(RELEASE_COMMIT_PREFIX))
+        .map(Version::from_arg)
+        .transpose()?;
+
+    if let Some(version) = &released_version {
+        if let Some(remote_branch) = pushed_to(repo, head.id())? {
+            return Err(anyhow!(
+                "{} was already pushed to {remote_branch}, rolling it back would rewrite published history: revert it with `git revert` instead",
+                version.as_tag()
+            ));
+        }
+
+        // Reverted first, edits that conflict with the release then leave everything as it was.
+        let reverted = revert_release(&repo.repository, &head)?;
+
+        let tag = version.as_tag();
+        let tagged_head = repo
+            .tags()
+            .iter()
+            .any(|tag| &tag.version == version && tag.commit_id == head.id());
+        if tagged_head {
+            git(&["tag", "-d", &tag])?;
+            println!("Deleted tag {tag}");
+        }
+
+        git(&["reset", "--soft", "HEAD^"])?;
+        let mut args = vec!["reset", "--quiet", "--"];
+        for path in &reverted {
+            args.push(path_arg(path)?);
+        }
+        git(&args)?;
+        println!("Dropped commit {RELEASE_COMMIT_PREFIX}{version}");
+        for path in &reverted {
+            println!("Reverted {}", path.display());
+        }
+
+        return Ok(());
+    }
+
+    // The files written by a release that failed before committing are found from the version
+    // code it wrote.
+    let root = PathBuf::from(PROJECT_DIR).join("..");
+    let version_fields = settings.version_source.locate_in(&root)?;
+    let version_code = version_fields.version_code(&root)?;
+    let locales = Locale::load_all(&root)?;
+    let changed = changed(
+        repo,
+        &released_paths(version_code, &locales, &version_fields, true),
+    );
+
+    if changed.is_empty() {
+        println!(
+            "Nothing to roll back, HEAD is not a release commit and no release file was changed"
+        );
+        return Ok(());
+    }
+    if !force && !partial_release(repo, settings, &root, &version_fields)? {
+        return Err(anyhow!(
+            "HEAD is not a release commit, and the version and CHANGELOG.md are the ones of HEAD: the changes of {} don't look like a release, they were kept. Use --force to restore them anyway",
+            changed
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+
+    restore(repo, &changed)?;
+    for path in &changed {
+        println!("Restored {}", path.display());
+    }
+
+    Ok(())
+}
+
+// Whether a release stopped before committing: it wrote a new version name, or a new section at
+// the top of CHANGELOG.md.
+fn partial_release(
+    repo: &Repo,
+    settings: &Settings,
+    root: &Path,
+    version_fields: &VersionFields,
+) -> Result<bool> {
+    let head_id = repo.repository.head()?.peel_to_commit()?.id();
+    let read_head = |path: &Path| repo.file_at(head_id, path);
+
+    let name = version_fields.version_name(root)?;
+    if settings
+        .version_source
+        .version_name_with(read_head)
+        .is_ok_and(|head_name| head_name != name)
+    {
+        return Ok(true);
+    }
+
+    let changelog_path = Path::new("CHANGELOG.md");
+    let changelog = root.join(changelog_path);
+    if !changelog.exists() {
+        return Ok(false);
+    }
+    let changelog = fs::read_to_string(changelog)?;
+    let head_changelog = read_head(changelog_path)?.unwrap_or_default();
+    let newest = changelog_file::newest_heading(&changelog);
+    Ok(newest.is_some() && newest != changelog_file::newest_heading(&head_changelog))
+}
+
+// Undoes the changes of the release commit in the working tree, keeping the edits made since.
+// Returns the reverted paths, or fails without changing anything if the edits overlap them.
+fn revert_release(repository: &Repository, release: &Commit) -> Result<Vec<PathBuf>> {
+    let diff = repository.diff_tree_to_tree(
+        Some(&release.tree()?),
+        Some(&release.parent(0)?.tree()?),
+        None,
+    )?;
+    let paths = diff
+        .deltas()
+        .filter_map(|delta| delta.old_file().path().or(delta.new_file().path()))
+        .map(Path::to_path_buf)
+        .collect();
+    repository
+        .apply(&diff, ApplyLocation::WorkDir, None)
+        .map_err(|error| {
+            anyhow!(
+                "The release files were edited since the release, in a way that conflicts with it: commit or stash these edits first ({})",
+                error.message()
+            )
+        })?;
+
+    Ok(paths)
+}
+
+// The remote branch that already contains the commit, if any.
+fn pushed_to(repo: &Repo, commit_id: Oid) -> Result<Option<String>> {
+    for branch in repo.repository.branches(Some(BranchType::Remote))? {
+        let (branch, _) = branch?;
+        let Some(target) = branch.get().target() else {
+            continue;
+        };
+        if target == commit_id || repo.repository.graph_descendant_of(target, commit_id)? {
+            return Ok(Some(branch.name()?.unwrap_or_default().to_string()));
+        }
+    }
+
+    Ok(None)
+}
+
+// The paths that differ from HEAD, staged or not.
+fn changed(repo: &Repo, paths: &[PathBuf]) -> Vec<PathBuf> {
+    paths
+        .iter()
+        .filter(|path| {
+            repo.repository
+                .status_file(path)
+                .is_ok_and(|status| status != Status::CURRENT && status != Status::IGNORED)
+        })
+        .cloned()
+        .collect()
+}
+
+// Puts the paths back as they are in HEAD, removing the ones HEAD doesn't have.
+fn restore(repo: &Repo, paths: &[PathBuf]) -> Result<()> {
+    let root = PathBuf::from(PROJECT_DIR).join("..");
+    let head_tree = repo.repository.head()?.peel_to_tree()?;
+
+    for path in paths {
+        let path_arg = path_arg(path)?;
+        if head_tree.get_path(path).is_ok() {
+            git(&["checkout", "HEAD", "--", path_arg])?;
+        } else {
+            git(&[
+                "rm",
+                "--cached",
+                "--quiet",
+                "--ignore-unmatch",
+                "--",
+                path_arg,
+            ])?;
+            let file = root.join(path);
+            if file.exists() {
+                fs::remove_file(file)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn path_arg(path: &Path) -> Result<&str> {
+    path.to_str()
+        .ok_or_else(|| anyhow!("{} is not a valid path", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{IndexAddOption, Signature};
+
+    const BUILD_GRADLE: &str = "android {
+    defaultConfig {
+        versionCode 10400
+        versionName \"1.4.0\"
+    }
+}
+
+dependencies {
+    implementation 'androidx.core:core-ktx:1.12.0'
+}
+";
+    const CHANGELOG: &str = "# Changelog
+
+## [1.4.0] - 2024-05-01
+
+### Features
+
+- Pick a color
+";
+
+    // A repository with a release commit on top of the previous one.
+    fn repository(name: &str) -> (PathBuf, Repository) {
+        let root = std::env::temp_dir().join(format!("rollback-{name}-{}", std::process::id()));
+        if root.exists() {
+            fs::remove_dir_all(&root).unwrap();
+        }
+        let repository = Repository::init(&root).unwrap();
+        fs::write(root.join("build.gradle"), BUILD_GRADLE).unwrap();
+        fs::write(root.join("CHANGELOG.md"), CHANGELOG).unwrap();
+        commit(&repository, "feat: pick a color");
+
+        let build_gradle = BUILD_GRADLE
+            .replace("10400", "10500")
+            .replace("1.4.0", "1.5.0");
+        fs::write(root.join("build.gradle"), build_gradle).unwrap();
+        let changelog = CHANGELOG.replace(
+            "## [1.4.0]",
+            "## [1.5.0] - 2024-06-01\n\n### Bug Fixes\n\n- Crop the preview\n\n## [1.4.0]",
+        );
+        fs::write(root.join("CHANGELOG.md"), changelog).unwrap();
+        fs::create_dir_all(root.join("changelogs")).unwrap();
+        fs::write(root.join("changelogs/10500.txt"), "Crop the preview\n").unwrap();
+        commit(&repository, "chore(release): 1.5.0");
+
+        (root, repository)
+    }
+
+    fn commit(repository: &Repository, message: &str) {
+        let mut index = repository.index().unwrap();
+        index.add_all(["*"], IndexAddOption::DEFAULT, None).unwrap();
+        index.write().unwrap();
+        let tree = repository.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("Release", "release@example.com").unwrap();
+        let parent = repository
+            .head()
+            .ok()
+            .map(|head| head.peel_to_commit().unwrap());
+        let parents: Vec<_> = parent.iter().collect();
+        repository
+            .commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                message,
+                &tree,
+                &parents,
+            )
+            .unwrap();
+    }
+
+    fn release(repository: &Repository) -> Commit<'_> {
+        repository.head().unwrap().peel_to_commit().unwrap()
+    }
+
+    #[test]
+    fn revert_release_undoes_the_release_changes() {
+        let (root, repository) = repository("undo");
+
+        let reverted = revert_release(&repository, &release(&repository)).unwrap();
+
+        assert_eq!(
+            reverted,
+            [
+                PathBuf::from("CHANGELOG.md"),
+                PathBuf::from("build.gradle"),
+                PathBuf::from("changelogs/10500.txt"),
+            ]
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("build.gradle")).unwrap(),
+            BUILD_GRADLE
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("CHANGELOG.md")).unwrap(),
+            CHANGELOG
+        );
+        assert!(!root.join("changelogs/10500.txt").exists());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn revert_release_keeps_unrelated_edits() {
+        let (root, repository) = repository("keep");
+        let edited = fs::read_to_string(root.join("build.gradle"))
+            .unwrap()
+            .replace("core-ktx:1.12.0", "core-ktx:1.13.1");
+        fs::write(root.join("build.gradle"), edited).unwrap();
+        fs::write(root.join("notes.txt"), "Work in progress\n").unwrap();
+
+        revert_release(&repository, &release(&repository)).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(root.join("build.gradle")).unwrap(),
+            BUILD_GRADLE.replace("core-ktx:1.12.0", "core-ktx:1.13.1")
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("notes.txt")).unwrap(),
+            "Work in progress\n"
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn revert_release_refuses_conflicting_edits() {
+        let (root, repository) = repository("conflict");
+        let edited = fs::read_to_string(root.join("build.gradle"))
+            .unwrap()
+            .replace("\"1.5.0\"", "\"1.5.1\"");
+        fs::write(root.join("build.gradle"), &edited).unwrap();
+
+        assert!(revert_release(&repository, &release(&repository)).is_err());
+        assert_eq!(
+            fs::read_to_string(root.join("build.gradle")).unwrap(),
+            edited
+        );
+        assert!(root.join("changelogs/10500.txt").exists());
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,45 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::bump::BumpSettings;
+use crate::version_code::VersionCodeSettings;
+use crate::version_source::VersionSource;
+
+// The content of release.toml, every field has a default so the file can be partial or missing.
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub bump: BumpSettings,
+    pub release_notes: ReleaseNotes,
+    pub version_code: VersionCodeSettings,
+    pub version_source: VersionSource,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReleaseNotes {
+    pub max_length: usize,
+    pub priority: Vec<String>,
+    pub more: String,
+}
+
+impl Default for ReleaseNotes {
+    fn default() -> Self {
+        Self {
+            max_length: 500,
+            priority: vec!["Features".to_string(), "Bug Fixes".to_string()],
+            more: "- And {count} more".to_string(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn read(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path)?;
+        toml::from_str(&content).with_context(|| format!("Couldn't parse {}", path.display()))
+    }
+}
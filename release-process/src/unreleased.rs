@@ -0,0 +1,29 @@
+use anyhow::Result;
+use git_cliff_core::changelog::Changelog;
+use git_cliff_core::config::Config;
+
+use crate::bump::BumpSettings;
+use crate::version::VersionExt;
+use crate::{changelog_file, next_version, Channel, Release, Repo};
+
+// The [unreleased] section of CHANGELOG.md, after the commits that next_version ignores and the
+// one that decides the bump.
+pub fn unreleased(repo: &Repo, config: &Config, bump_settings: &BumpSettings) -> Result<()> {
+    match repo.last_release() {
+        Some(last_release) => println!("Last release: {}", last_release.version.as_tag()),
+        None => println!("No version tagged yet"),
+    }
+    match next_version(repo, &Channel::Release, None, bump_settings)? {
+        Some((next_version, _)) => println!("Next version: {}", next_version.as_tag()),
+        None => println!("Nothing to release"),
+    }
+
+    let head_id = repo.repository.head()?.peel_to_commit()?.id();
+    let release = Release::new(None, head_id, repo.last_release()).as_cliff_release(repo)?;
+    let sections_config = changelog_file::sections_config(config);
+    let mut section = Vec::new();
+    Changelog::new(vec![release], &sections_config)?.generate(&mut section)?;
+    println!("\n{}", String::from_utf8(section)?);
+
+    Ok(())
+}
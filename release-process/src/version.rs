@@ -0,0 +1,83 @@
+use anyhow::{anyhow, Result};
+use semver::{BuildMetadata, Prerelease, Version};
+use serde::Serialize;
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, clap::ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Bump {
+    Patch,
+    Minor,
+    Major,
+}
+
+pub trait VersionExt: Sized {
+    fn from_tag(tag: &str) -> Result<Self>;
+
+    // Parses a version typed by the user, with or without the leading v.
+    fn from_arg(arg: &str) -> Result<Self>;
+
+    fn as_tag(&self) -> String;
+
+    fn is_prerelease(&self) -> bool;
+
+    fn bump(&self, bump: Bump) -> Self;
+
+    // The next `<identifier>.N` prerelease of this version, following `previous` if it was a
+    // prerelease of the same version with the same identifier.
+    fn next_prerelease(&self, identifier: &str, previous: Option<&Self>) -> Result<Self>;
+
+    fn promote(&self) -> Self;
+}
+
+impl VersionExt for Version {
+    fn from_tag(tag: &str) -> Result<Self> {
+        let version = tag
+            .strip_prefix('v')
+            .ok_or_else(|| anyhow!("Tag {tag} doesn't start with a v"))?;
+
+        Ok(Version::parse(version)?)
+    }
+
+    fn from_arg(arg: &str) -> Result<Self> {
+        Ok(Version::parse(arg.strip_prefix('v').unwrap_or(arg))?)
+    }
+
+    fn as_tag(&self) -> String {
+        format!("v{self}")
+    }
+
+    fn is_prerelease(&self) -> bool {
+        !self.pre.is_empty()
+    }
+
+    fn bump(&self, bump: Bump) -> Self {
+        match bump {
+            Bump::Major => Version::new(self.major + 1, 0, 0),
+            Bump::Minor => Version::new(self.major, self.minor + 1, 0),
+            Bump::Patch => Version::new(self.major, self.minor, self.patch + 1),
+        }
+    }
+
+    fn next_prerelease(&self, identifier: &str, previous: Option<&Self>) -> Result<Self> {
+        let number = previous
+            .filter(|previous| {
+                (previous.major, previous.minor, previous.patch)
+                    == (self.major, self.minor, self.patch)
+            })
+            .and_then(|previous| previous.pre.as_str().strip_prefix(identifier))
+            .and_then(|number| number.strip_prefix('.'))
+            .and_then(|number| number.parse::<u64>().ok())
+            .map_or(1, |number| number + 1);
+
+        let mut version = Version::new(self.major, self.minor, self.patch);
+        version.pre = Prerelease::new(&format!("{identifier}.{number}"))?;
+        Ok(version)
+    }
+
+    fn promote(&self) -> Self {
+        let mut version = self.clone();
+        version.pre = Prerelease::EMPTY;
+        version.build = BuildMetadata::EMPTY;
+        version
+    }
+}
@@ -0,0 +1,115 @@
+use anyhow::{anyhow, Result};
+use semver::Version;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+use crate::version::VersionExt;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Strategy {
+    // The current version code plus one.
+    #[default]
+    Increment,
+    // major * 10000 + minor * 100 + patch. Prereleases would get the code of their final version,
+    // so they are refused.
+    Semver,
+    // (major * 10000 + minor * 100 + patch) * 100 + slot. The slot of a prerelease is the offset
+    // of its identifier plus its number, the one of a final version is 99, so that the codes of
+    // the betas of a version are lower than the final one.
+    Prerelease,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct VersionCodeSettings {
+    pub strategy: Strategy,
+    pub prerelease_offsets: BTreeMap<String, u64>,
+}
+
+impl Default for VersionCodeSettings {
+    fn default() -> Self {
+        Self {
+            strategy: Strategy::default(),
+            prerelease_offsets: BTreeMap::from([
+                ("alpha".to_string(), 0),
+                ("beta".to_string(), 30),
+                ("rc".to_string(), 60),
+            ]),
+        }
+    }
+}
+
+const FINAL_SLOT: u64 = 99;
+
+impl VersionCodeSettings {
+    pub fn next_version_code(&self, current: u64, version: &Version) -> Result<u64> {
+        match self.strategy {
+            Strategy::Increment => Ok(current + 1),
+            Strategy::Semver => {
+                if version.is_prerelease() {
+                    return Err(anyhow!(
+                        "{} would get the version code of its final version with the semver strategy, use the prerelease strategy in release.toml",
+                        version.as_tag()
+                    ));
+                }
+                base(version)
+            }
+            Strategy::Prerelease => Ok(base(version)? * 100 + self.slot(version)?),
+        }
+    }
+
+    fn slot(&self, version: &Version) -> Result<u64> {
+        if !version.is_prerelease() {
+            return Ok(FINAL_SLOT);
+        }
+
+        let (identifier, number) = version.pre.as_str().split_once('.').ok_or_else(|| {
+            anyhow!(
+                "{} is not like <identifier>.<number>, it has no version code",
+                version.as_tag()
+            )
+        })?;
+        let number: u64 = number.parse().map_err(|_| {
+            anyhow!(
+                "{} doesn't end with a prerelease number, it has no version code",
+                version.as_tag()
+            )
+        })?;
+        let offset = *self.prerelease_offsets.get(identifier).ok_or_else(|| {
+            anyhow!(
+                "No version code offset for {identifier} prereleases, add it to prerelease_offsets in release.toml (known: {})",
+                self.prerelease_offsets.keys().cloned().collect::<Vec<_>>().join(", ")
+            )
+        })?;
+        // The slots of an identifier end where the ones of the next identifier start.
+        let end = self
+            .prerelease_offsets
+            .values()
+            .copied()
+            .filter(|other| *other > offset)
+            .min()
+            .unwrap_or(FINAL_SLOT);
+
+        let slot = offset + number;
+        if number == 0 || slot >= end {
+            return Err(anyhow!(
+                "{} doesn't fit in the version codes of {identifier} prereleases, from {} to {}",
+                version.as_tag(),
+                offset + 1,
+                end - 1
+            ));
+        }
+        Ok(slot)
+    }
+}
+
+fn base(version: &Version) -> Result<u64> {
+    if version.minor >= 100 || version.patch >= 100 {
+        return Err(anyhow!(
+            "{} can't be turned into a version code, the minor and patch numbers must be lower than 100",
+            version.as_tag()
+        ));
+    }
+    Ok(version.major * 10000 + version.minor * 100 + version.patch)
+}
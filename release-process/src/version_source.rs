@@ -0,0 +1,208 @@
+use anyhow::{anyhow, Result};
+use regex::{Captures, Regex};
+use semver::Version;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+// Groovy `versionName "2.2.0"` or `versionName = "2.2.0"`, Kotlin `versionName = "2.2.0"`.
+const GRADLE_NAME: &str = r#"(?m)^(\s*versionName\s*=?\s*["'])([0-9][^"'\s]*)(["'])"#;
+const GRADLE_CODE: &str = r"(?m)^(\s*versionCode\s*=?\s*)([0-9]+)()";
+// `VERSION_NAME=2.2.0` in gradle.properties.
+const PROPERTIES_NAME: &str = r"(?m)^(\s*VERSION_NAME\s*[=:]\s*)([0-9]\S*)()";
+const PROPERTIES_CODE: &str = r"(?m)^(\s*VERSION_CODE\s*[=:]\s*)([0-9]+)()";
+// `versionName = "2.2.0"` in the [versions] of a version catalog, like libs.versions.toml.
+const CATALOG_NAME: &str =
+    r#"(?m)^(\s*(?:versionName|version-name|version_name)\s*=\s*")([0-9][^"]*)(")"#;
+const CATALOG_CODE: &str =
+    r#"(?m)^(\s*(?:versionCode|version-code|version_code)\s*=\s*"?)([0-9]+)("?)"#;
+
+// The files where the versionName and versionCode of the app are looked for. They can be in two
+// different files, but each must be found exactly once.
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct VersionSource {
+    pub files: Vec<PathBuf>,
+}
+
+impl Default for VersionSource {
+    fn default() -> Self {
+        Self {
+            files: vec![
+                PathBuf::from("app/build.gradle"),
+                PathBuf::from("app/build.gradle.kts"),
+                PathBuf::from("gradle.properties"),
+                PathBuf::from("gradle/libs.versions.toml"),
+            ],
+        }
+    }
+}
+
+// Where the versionName and versionCode were found, relative to the root of the repository.
+pub struct VersionFields {
+    name: Field,
+    code: Field,
+}
+
+struct Field {
+    path: PathBuf,
+    pattern: Regex,
+}
+
+impl VersionSource {
+    // `read` gives the content of a file, or None if it doesn't exist.
+    pub fn locate<F>(&self, read: F) -> Result<VersionFields>
+    where
+        F: Fn(&Path) -> Result<Option<String>>,
+    {
+        let mut names = Vec::new();
+        let mut codes = Vec::new();
+        for path in &self.files {
+            let Some(content) = read(path)? else {
+                continue;
+            };
+            let (name, code) = patterns(path)?;
+            for _ in name.find_iter(&content) {
+                names.push(Field {
+                    path: path.clone(),
+                    pattern: name.clone(),
+                });
+            }
+            for _ in code.find_iter(&content) {
+                codes.push(Field {
+                    path: path.clone(),
+                    pattern: code.clone(),
+                });
+            }
+        }
+
+        Ok(VersionFields {
+            name: self.single("versionName", names)?,
+            code: self.single("versionCode", codes)?,
+        })
+    }
+
+    // The version code of files read with `read`, like the ones of a tagged commit.
+    pub fn version_code_with<F>(&self, read: F) -> Result<u64>
+    where
+        F: Fn(&Path) -> Result<Option<String>>,
+    {
+        let fields = self.locate(&read)?;
+        let content = read(&fields.code.path)?
+            .ok_or_else(|| anyhow!("{} disappeared", fields.code.path.display()))?;
+        fields.version_code_in(&content)
+    }
+
+    // The version name of files read with `read`.
+    pub fn version_name_with<F>(&self, read: F) -> Result<String>
+    where
+        F: Fn(&Path) -> Result<Option<String>>,
+    {
+        let fields = self.locate(&read)?;
+        let content = read(&fields.name.path)?
+            .ok_or_else(|| anyhow!("{} disappeared", fields.name.path.display()))?;
+        fields.name.value_in(&content)
+    }
+
+    pub fn locate_in(&self, root: &Path) -> Result<VersionFields> {
+        self.locate(|path| {
+            let file = root.join(path);
+            Ok(file
+                .exists()
+                .then(|| std::fs::read_to_string(file))
+                .transpose()?)
+        })
+    }
+
+    fn single(&self, field: &str, mut found: Vec<Field>) -> Result<Field> {
+        match found.len() {
+            1 => Ok(found.remove(0)),
+            0 => Err(anyhow!(
+                "Couldn't find the {field} in {}, add the file to [version_source] in release-process/release.toml",
+                self.display_files()
+            )),
+            _ => Err(anyhow!(
+                "Found the {field} {} times, in {}: keep a single one so that releases don't update the wrong one",
+                found.len(),
+                found
+                    .iter()
+                    .map(|field| field.path.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        }
+    }
+
+    fn display_files(&self) -> String {
+        self.files
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl Field {
+    fn value_in(&self, content: &str) -> Result<String> {
+        let captures = self
+            .pattern
+            .captures(content)
+            .ok_or_else(|| anyhow!("Couldn't find the version in {}", self.path.display()))?;
+        Ok(captures[2].to_string())
+    }
+}
+
+impl VersionFields {
+    fn version_code_in(&self, content: &str) -> Result<u64> {
+        Ok(self.code.value_in(content)?.parse::<u64>()?)
+    }
+
+    pub fn version_name(&self, root: &Path) -> Result<String> {
+        self.name
+            .value_in(&std::fs::read_to_string(root.join(&self.name.path))?)
+    }
+
+    pub fn version_code(&self, root: &Path) -> Result<u64> {
+        self.version_code_in(&std::fs::read_to_string(root.join(&self.code.path))?)
+    }
+
+    // The files to commit with a release, without duplicates.
+    pub fn paths(&self) -> Vec<PathBuf> {
+        let mut paths = vec![self.name.path.clone()];
+        if self.code.path != self.name.path {
+            paths.push(self.code.path.clone());
+        }
+        paths
+    }
+
+    pub fn write(&self, root: &Path, version: &Version, version_code: u64) -> Result<()> {
+        replace(root, &self.name, &version.to_string())?;
+        replace(root, &self.code, &version_code.to_string())
+    }
+}
+
+fn replace(root: &Path, field: &Field, value: &str) -> Result<()> {
+    let file = root.join(&field.path);
+    let content = std::fs::read_to_string(&file)?;
+    let content = field.pattern.replace(&content, |caps: &Captures| {
+        format!("{}{}{}", &caps[1], value, &caps[3])
+    });
+    std::fs::write(file, content.as_bytes())?;
+
+    Ok(())
+}
+
+fn patterns(path: &Path) -> Result<(Regex, Regex)> {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    let (name, code) = if file_name.ends_with(".properties") {
+        (PROPERTIES_NAME, PROPERTIES_CODE)
+    } else if file_name.ends_with(".toml") {
+        (CATALOG_NAME, CATALOG_CODE)
+    } else {
+        (GRADLE_NAME, GRADLE_CODE)
+    };
+
+    Ok((Regex::new(name)?, Regex::new(code)?))
+}
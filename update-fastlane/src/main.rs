@@ -1,12 +1,25 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use serde::Deserialize;
 use std::{
+    collections::BTreeSet,
     fs::{self, DirEntry, OpenOptions},
     io::Write,
     path::{Path, PathBuf},
 };
 
 const PROJECT_DIR: &str = env!("CARGO_MANIFEST_DIR");
+// The locale whose images the Play Store shows when a locale has none.
+const DEFAULT_LOCALE: &str = "en-US";
+// The image types supply uploads from `images`: single images, whatever their extension, and
+// directories of screenshots.
+const IMAGES: [&str; 4] = ["featureGraphic", "icon", "promoGraphic", "tvBanner"];
+const SCREENSHOT_DIRECTORIES: [&str; 5] = [
+    "phoneScreenshots",
+    "sevenInchScreenshots",
+    "tenInchScreenshots",
+    "tvScreenshots",
+    "wearScreenshots",
+];
 
 #[derive(Deserialize)]
 struct StoreInfo {
@@ -16,6 +29,71 @@ struct StoreInfo {
     pub full_description: Option<String>,
     #[serde(rename = "store_short_description")]
     pub short_description: Option<String>,
+    #[serde(rename = "store_video")]
+    pub video: Option<String>,
+    #[serde(rename = "store_default_changelog")]
+    pub default_changelog: Option<String>,
+}
+
+// A store listing field, with the file fastlane reads it from and its Play Store length limit.
+struct Field<'a> {
+    key: &'static str,
+    file: &'static str,
+    limit: Option<usize>,
+    value: &'a Option<String>,
+}
+
+impl StoreInfo {
+    fn fields(&self) -> [Field<'_>; 5] {
+        [
+            Field {
+                key: "app_name",
+                file: "title.txt",
+                limit: Some(30),
+                value: &self.title,
+            },
+            Field {
+                key: "store_full_description",
+                file: "full_description.txt",
+                limit: Some(4000),
+                value: &self.full_description,
+            },
+            Field {
+                key: "store_short_description",
+                file: "short_description.txt",
+                limit: Some(80),
+                value: &self.short_description,
+            },
+            Field {
+                key: "store_video",
+                file: "video.txt",
+                limit: None,
+                value: &self.video,
+            },
+            Field {
+                key: "store_default_changelog",
+                file: "changelogs/default.txt",
+                limit: Some(500),
+                value: &self.default_changelog,
+            },
+        ]
+    }
+
+    fn length_errors(&self, filename: &str) -> Vec<String> {
+        self.fields()
+            .iter()
+            .filter_map(|field| {
+                let length = field.value.as_ref()?.chars().count();
+                let limit = field.limit?;
+                (length > limit).then(|| {
+                    format!(
+                        "{filename}: {} is {length} characters long, the Play Store limit is {limit}",
+                        field.key
+                    )
+                })
+            })
+            .collect()
+    }
 }
 
 fn main() -> Result<()> {
@@ -31,45 +109,108 @@ fn main() -> Result<()> {
         })
         .collect::<Vec<_>>();
 
+    let mut entries = Vec::new();
     for entry in files {
-        update_fastlane_for_entry(entry)?;
+        let content = fs::read_to_string(entry.path())?;
+        let store_info = serde_json::from_str::<StoreInfo>(&content)?;
+        entries.push((entry, store_info));
+    }
+
+    // Don't write anything if a single listing would be rejected by the Play Store.
+    let errors: Vec<_> = entries
+        .iter()
+        .flat_map(|(entry, store_info)| {
+            store_info.length_errors(&entry.file_name().to_string_lossy())
+        })
+        .collect();
+    if !errors.is_empty() {
+        for error in &errors {
+            eprintln!("{error}");
+        }
+        return Err(anyhow!(
+            "{} store listing field(s) are too long",
+            errors.len()
+        ));
+    }
+
+    let mut locales = Vec::new();
+    for (entry, store_info) in entries {
+        locales.push(update_fastlane_for_entry(entry, &store_info)?);
+    }
+
+    let default_images = image_types(&locale_directory(DEFAULT_LOCALE))?;
+    for locale in locales.iter().filter(|locale| *locale != DEFAULT_LOCALE) {
+        let images = image_types(&locale_directory(locale))?;
+        let missing: Vec<_> = default_images.difference(&images).copied().collect();
+        if !missing.is_empty() {
+            println!(
+                "{locale}: no {}, the Play Store will show the {DEFAULT_LOCALE} ones",
+                missing.join(", ")
+            );
+        }
     }
 
     Ok(())
 }
 
-fn update_fastlane_for_entry(entry: DirEntry) -> Result<()> {
+// Returns the locale of the entry, the name of its fastlane directory.
+fn update_fastlane_for_entry(entry: DirEntry, store_info: &StoreInfo) -> Result<String> {
     let filename = entry.file_name();
     let filename = filename.to_string_lossy();
 
-    let content = fs::read_to_string(entry.path())?;
-
-    let store_info = serde_json::from_str::<StoreInfo>(&content)?;
-
     let dirname = filename.trim_end_matches(".json");
-    let dir_path = PathBuf::from(PROJECT_DIR)
-        .join("../fastlane/metadata/android")
-        .join(dirname);
+    let dir_path = locale_directory(dirname);
 
     fs::create_dir_all(&dir_path)?;
 
-    if let Some(title) = &store_info.title {
-        write_file(&dir_path, "title.txt", title)?;
+    for field in store_info.fields() {
+        if let Some(value) = field.value {
+            write_file(&dir_path, field.file, value)?;
+        }
     }
 
-    if let Some(full_description) = &store_info.full_description {
-        write_file(&dir_path, "full_description.txt", full_description)?;
+    Ok(dirname.to_string())
+}
+
+fn locale_directory(locale: &str) -> PathBuf {
+    PathBuf::from(PROJECT_DIR)
+        .join("../fastlane/metadata/android")
+        .join(locale)
+}
+
+// The image types found in the `images` directory of a locale, screenshot directories only count
+// when they aren't empty.
+fn image_types(locale_directory: &Path) -> Result<BTreeSet<&'static str>> {
+    let mut types = BTreeSet::new();
+    let images = locale_directory.join("images");
+    if !images.is_dir() {
+        return Ok(types);
     }
 
-    if let Some(short_description) = &store_info.short_description {
-        write_file(&dir_path, "short_description.txt", short_description)?;
+    for entry in fs::read_dir(images)? {
+        let path = entry?.path();
+        let Some(stem) = path.file_stem().map(|stem| stem.to_string_lossy()) else {
+            continue;
+        };
+        let image_type = if path.is_dir() {
+            SCREENSHOT_DIRECTORIES
+                .into_iter()
+                .find(|directory| *directory == stem)
+                .filter(|_| fs::read_dir(&path).is_ok_and(|mut files| files.next().is_some()))
+        } else {
+            IMAGES.into_iter().find(|image| *image == stem)
+        };
+        types.extend(image_type);
     }
 
-    Ok(())
+    Ok(types)
 }
 
 fn write_file(directory: &Path, filename: &str, content: &str) -> Result<()> {
     let file_path = directory.join(filename);
+    if let Some(parent) = file_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
     let mut file = OpenOptions::new()
         .write(true)
         .create(true)